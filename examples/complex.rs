@@ -7,41 +7,21 @@ use iron::middleware::{ Handler };
 
 use iron_pipeline::prelude::*;
 use iron_pipeline::{ Middleware, PipelineNext };
-
-fn log_request(req: &Request) {
-    println!("{} {}", req.method, req.url);
-}
-
-fn log_response(res: &Response) {
-    match res.status {
-        Some(status) => println!("{}", status),
-        None         => println!("No response status set")
-    }
-}
+use iron_pipeline::predicate::Header;
+use iron_pipeline::middleware::access_log::{ AccessLog, LogFormat };
+use iron_pipeline::middleware::request_id::AssignRequestId;
 
 fn main() {
     
     let mut pipeline = Pipeline::new();
 
-    // This middleware runs on all requests
-    pipeline.add(HandleNext(|req, next| {
-        // Log the request and response
-        log_request(req);
-        match next.process(req) {
-            Ok(res) => {
-                log_response(&res);
-                Ok(res)
-            },
-            Err(err) => {
-                println!("Error {}", err.error);
-                log_response(&err.response);
-                Err(err)
-            }
-        }
-    }));
+    // This middleware runs on all requests, and logs each request and response
+    pipeline.add(AccessLog::stdout(LogFormat::Template(
+        "{remote_addr} {request_id} \"{method} {path}\" {status} {bytes} {duration_ms}ms".to_string())));
+    pipeline.add(AssignRequestId::new());
     
     // Example of forking on a predicate 
-    pipeline.add(Fork::when(|req| request_has_header(req, "X-ApiVersion", b"2009-01-01"), |v1| {
+    pipeline.add(Fork::when_matches(Header("X-ApiVersion", "2009-01-01"), |v1| {
         // This middleware runs only on requests with the correct X-ApiVersion header
         v1.add(WwwAuthenticate { username: "v1", password: "password" });
         v1.add(ApiV1Handler);
@@ -54,11 +34,11 @@ fn main() {
         v2.add(ApiV2Handler);
     }));
 
-    // Example inline handler - returns a 404 to all requests which did not 
+    // Example fallback handler - returns a 404 to all requests which did not 
     // match one of the above forks
-    pipeline.add(Handle(|_| {
+    pipeline.set_fallback(Fallback::with(Handle(|_| {
         Ok(Response::with((status::NotFound, "Not Found")))
-    }));
+    })));
     
     let port = 1337;
     let _listener =
@@ -69,16 +49,6 @@ fn main() {
     println!("Listening on port {}", port);
 }
 
-/// Utility function for checking a custom header value
-fn request_has_header(req: &Request, header_name: &str, header_value: &[u8]) -> bool {
-    if let Some(values) = req.headers.get_raw(header_name) {
-        values.iter().all(|v| v == &header_value)
-    }
-    else {
-        false
-    }
-}
-
 /// Simple Middleware which challenges all 
 /// requests for the configured username and password
 struct WwwAuthenticate {
@@ -90,6 +60,7 @@ impl Middleware for WwwAuthenticate {
     fn process(&self, req: &mut Request, next: PipelineNext) -> IronResult<Response> {
         use iron::headers::{ Authorization, Basic };
         
+        #[allow(clippy::needless_borrowed_reference)]
         let user_authorized =
             match req.headers.get::<Authorization<Basic>>() {
                 Some(&Authorization(ref basic)) => {
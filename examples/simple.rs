@@ -37,6 +37,7 @@ fn log_request(req: &Request) {
     println!("{} {}", req.method, req.url);
 }
 
+#[allow(clippy::match_ref_pats, clippy::needless_borrowed_reference)]
 fn log_response(result: &IronResult<Response>) {
     fn log_status(res: &Response) {
         match res.status {
@@ -0,0 +1,254 @@
+//! Build-time validation of pipelines.
+//!
+//! `Pipeline::build` checks a pipeline for mistakes which would leave some of
+//! its middleware unreachable, and returns a frozen `CompiledPipeline` which can
+//! no longer be modified.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # extern crate iron;
+//! # extern crate iron_pipeline;
+//! # use iron::prelude::*;
+//! # use iron_pipeline::prelude::*;
+//! # fn main() {
+//! let mut pipeline = Pipeline::new();
+//! pipeline.add(Handle(|_| Ok(Response::with("Hello from iron-pipeline"))));
+//! // Never invoked, because `Handle` never invokes the next middleware
+//! pipeline.add(Handle(|_| Ok(Response::with("Unreachable"))));
+//!
+//! assert!(pipeline.build().is_err());
+//! # }
+//! ```
+
+use std::error;
+use std::fmt;
+
+use iron::prelude::*;
+use iron::middleware::Handler;
+
+use Pipeline;
+use describe::{PipelineDescription, MiddlewareDescription};
+
+/// The severity of a `Diagnostic`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    /// The pipeline is valid, but probably not what was intended
+    Warning,
+    /// The pipeline contains middleware which can never be invoked
+    Error
+}
+
+/// The kinds of problem detected when validating a pipeline.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DiagnosticKind {
+    /// The middleware is registered after a middleware which never
+    /// invokes the next middleware, e.g. an `iron::Handler`
+    Unreachable {
+        /// Location of the middleware which never invokes the next middleware
+        after: String
+    },
+    /// The nested pipeline has no middleware
+    EmptyBranch,
+    /// The path prefix of the middleware is matched by an earlier middleware
+    ShadowedPath {
+        /// The shadowed path prefix
+        path: String,
+        /// Location of the earlier middleware
+        by: String
+    }
+}
+
+/// A problem detected when validating a pipeline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// Location of the middleware within the pipeline, e.g. `#1 "api" > #0`
+    pub location: String,
+    /// The problem detected
+    pub kind: DiagnosticKind
+}
+
+impl Diagnostic {
+    /// The severity of the problem.
+    pub fn severity(&self) -> Severity {
+        match self.kind {
+            DiagnosticKind::Unreachable { .. }  => Severity::Error,
+            DiagnosticKind::EmptyBranch         => Severity::Warning,
+            DiagnosticKind::ShadowedPath { .. } => Severity::Error
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self.severity() {
+            Severity::Warning => write!(fmt, "warning: ")?,
+            Severity::Error   => write!(fmt, "error: ")?
+        }
+        match self.kind {
+            DiagnosticKind::Unreachable { ref after } => {
+                write!(fmt, "{}: unreachable, {} never invokes the next middleware", self.location, after)
+            },
+            DiagnosticKind::EmptyBranch => {
+                write!(fmt, "{}: nested pipeline is empty", self.location)
+            },
+            DiagnosticKind::ShadowedPath { ref path, ref by } => {
+                write!(fmt, "{}: path {} is shadowed by {}", self.location, path, by)
+            }
+        }
+    }
+}
+
+/// Error raised by `Pipeline::build` when validation fails.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildError {
+    /// All problems detected, including warnings
+    pub diagnostics: Vec<Diagnostic>
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "Pipeline build error")?;
+        for diagnostic in &self.diagnostics {
+            write!(fmt, "\n{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for BuildError {
+    fn description(&self) -> &'static str {
+        "Pipeline validation failed"
+    }
+}
+
+/// A validated pipeline which can no longer be modified.
+/// Created by `Pipeline::build`.
+pub struct CompiledPipeline {
+    pipeline: Pipeline,
+    warnings: Vec<Diagnostic>
+}
+
+impl CompiledPipeline {
+    /// Any warnings raised when validating the pipeline.
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
+
+    /// Describe the topology of the pipeline. See `Pipeline::describe`.
+    pub fn describe(&self) -> PipelineDescription {
+        self.pipeline.describe()
+    }
+}
+
+impl Handler for CompiledPipeline {
+    /// Invokes the request pipeline
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        self.pipeline.handle(req)
+    }
+}
+
+impl Pipeline {
+    /// Check the pipeline for problems, such as middleware which can never be invoked.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        validate_pipeline(&self.describe(), &[], &mut diagnostics);
+        diagnostics
+    }
+
+    /// Validate the pipeline, and freeze it if no errors are found.
+    /// Fails if any diagnostic with a severity of `Severity::Error` is found.
+    pub fn build(self) -> Result<CompiledPipeline, BuildError> {
+        let diagnostics = self.validate();
+        if diagnostics.iter().any(|d| d.severity() == Severity::Error) {
+            return Err(BuildError { diagnostics });
+        }
+        Ok(CompiledPipeline { pipeline: self, warnings: diagnostics })
+    }
+}
+
+fn validate_pipeline(pipeline: &PipelineDescription, location: &[String], diagnostics: &mut Vec<Diagnostic>) {
+
+    // Location of the first middleware which never invokes the next middleware
+    let mut terminal: Option<String> = None;
+
+    // Path prefixes which are always handled by an earlier middleware
+    let mut mounted: Vec<(Vec<&str>, String)> = Vec::new();
+
+    for (index, middleware) in pipeline.middlewares.iter().enumerate() {
+        let mut location = location.to_vec();
+        location.push(step(index, middleware));
+        let here = location.join(" > ");
+
+        if let Some(ref after) = terminal {
+            diagnostics.push(Diagnostic {
+                location: here.clone(),
+                kind: DiagnosticKind::Unreachable { after: after.clone() }
+            });
+        }
+
+        if let Some(ref path) = middleware.path {
+            let segments = path_segments(path);
+            if let Some((_, by)) = mounted.iter().find(|m| path_shadows(&m.0, &segments)) {
+                diagnostics.push(Diagnostic {
+                    location: here.clone(),
+                    kind: DiagnosticKind::ShadowedPath { path: path.clone(), by: by.clone() }
+                });
+            }
+            if !middleware.fallthrough {
+                mounted.push((segments, here.clone()));
+            }
+        }
+
+        for branch in &middleware.branches {
+            let mut location = location.clone();
+            if let Some(ref label) = branch.label {
+                location.push(format!("[{}]", label));
+            }
+            if branch.pipeline.middlewares.is_empty() {
+                diagnostics.push(Diagnostic {
+                    location: location.join(" > "),
+                    kind: DiagnosticKind::EmptyBranch
+                });
+            }
+            validate_pipeline(&branch.pipeline, &location, diagnostics);
+        }
+
+        if middleware.terminal && terminal.is_none() {
+            terminal = Some(here);
+        }
+    }
+}
+
+fn step(index: usize, middleware: &MiddlewareDescription) -> String {
+    match middleware.name {
+        Some(ref name) => format!("#{} \"{}\"", index, name),
+        None           => format!("#{}", index)
+    }
+}
+
+fn path_segments(path: &str) -> Vec<&str> {
+    path.split('/').filter(|s| !s.is_empty()).collect()
+}
+
+/// Determine if every request matched by the `later` fork path
+/// is also matched by the `earlier` fork path.
+fn path_shadows(earlier: &[&str], later: &[&str]) -> bool {
+    for (i, segment) in earlier.iter().enumerate() {
+        // Wildcards match all remaining segments
+        if segment.starts_with('*') {
+            return true;
+        }
+        match later.get(i) {
+            Some(other) if other.starts_with('*') => return false,
+            Some(other) => {
+                // Parameters match any one segment
+                if !segment.starts_with(':') && segment != other {
+                    return false;
+                }
+            },
+            None => return false
+        }
+    }
+    true
+}
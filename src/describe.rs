@@ -0,0 +1,251 @@
+//! Introspection of a pipeline's topology.
+//!
+//! `Pipeline::describe` returns a `PipelineDescription`: a tree of the registered
+//! middleware, their names, any fork predicates and the nested sub pipelines.
+//! A description may be rendered as indented text (via `Display`) or as JSON.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # extern crate iron;
+//! # extern crate iron_pipeline;
+//! # use iron::prelude::*;
+//! # use iron_pipeline::prelude::*;
+//! # fn main() {
+//! let mut pipeline = Pipeline::new();
+//! pipeline.add_named("api-v2", Fork::when_path("/api/v2", |v2| {
+//!     v2.add_named("hello", Handle(|_| Ok(Response::with("Hello from iron-pipeline"))));
+//! }));
+//!
+//! let description = pipeline.describe();
+//! println!("{}", description);
+//! println!("{}", description.to_json());
+//! # }
+//! ```
+
+use std::any;
+use std::fmt;
+
+use json;
+
+/// Description of a pipeline and each of its middleware, in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PipelineDescription {
+    /// Descriptions of each middleware, in the order in which they were registered
+    pub middlewares: Vec<MiddlewareDescription>,
+    /// Description of the pipeline fallback
+    pub fallback: String
+}
+
+/// Description of a single middleware within a pipeline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MiddlewareDescription {
+    /// The name the middleware was registered with, if any
+    pub name: Option<String>,
+    /// The kind of middleware, usually its type name
+    pub kind: String,
+    /// Additional detail, such as a fork predicate or path prefix
+    pub detail: Option<String>,
+    /// The path prefix under which the nested pipelines are mounted, if any
+    pub path: Option<String>,
+    /// True if the middleware never invokes the next middleware
+    pub terminal: bool,
+    /// True if requests not handled by the nested pipelines are passed
+    /// on to the next middleware
+    pub fallthrough: bool,
+    /// Any nested pipelines which the middleware may delegate to
+    pub branches: Vec<BranchDescription>
+}
+
+/// Description of a nested pipeline.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BranchDescription {
+    /// Label which identifies the branch, if the middleware has more than one
+    pub label: Option<String>,
+    /// Description of the nested pipeline
+    pub pipeline: PipelineDescription
+}
+
+impl MiddlewareDescription {
+    /// Construct a new description of the given kind of middleware.
+    pub fn new<K>(kind: K) -> MiddlewareDescription
+        where K: Into<String>
+    {
+        MiddlewareDescription {
+            name: None,
+            kind: kind.into(),
+            detail: None,
+            path: None,
+            terminal: false,
+            fallthrough: false,
+            branches: Vec::new()
+        }
+    }
+
+    /// Construct a new description using the (shortened) type name of `T`.
+    pub fn of<T: ?Sized>() -> MiddlewareDescription {
+        MiddlewareDescription::new(short_type_name(any::type_name::<T>()))
+    }
+
+    /// Set the detail of this description.
+    pub fn detail<D>(mut self, detail: D) -> MiddlewareDescription
+        where D: Into<String>
+    {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// Set the path prefix of this description.
+    pub fn path<P>(mut self, path: P) -> MiddlewareDescription
+        where P: Into<String>
+    {
+        self.path = Some(path.into());
+        self
+    }
+
+    /// Mark the described middleware as never invoking the next middleware.
+    pub fn terminal(mut self) -> MiddlewareDescription {
+        self.terminal = true;
+        self
+    }
+
+    /// Mark the described middleware as passing requests not handled by
+    /// its nested pipelines on to the next middleware.
+    pub fn fallthrough(mut self) -> MiddlewareDescription {
+        self.fallthrough = true;
+        self
+    }
+
+    /// Add a nested pipeline to this description.
+    pub fn branch(mut self, label: Option<String>, pipeline: PipelineDescription) -> MiddlewareDescription {
+        self.branches.push(BranchDescription { label, pipeline });
+        self
+    }
+}
+
+/// Strip module paths from a type name, e.g.
+/// `iron_pipeline::middleware::handle::Handle<app::main::{{closure}}>` becomes `Handle<{{closure}}>`
+pub(crate) fn short_type_name(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    let mut segment_start = 0;
+    for (i, c) in name.char_indices() {
+        if "<>,()[]&; ".contains(c) {
+            result.push_str(last_path_segment(&name[segment_start..i]));
+            result.push(c);
+            segment_start = i + c.len_utf8();
+        }
+    }
+    result.push_str(last_path_segment(&name[segment_start..]));
+    result
+}
+
+fn last_path_segment(path: &str) -> &str {
+    match path.rfind("::") {
+        Some(idx) => &path[idx + 2..],
+        None      => path
+    }
+}
+
+impl PipelineDescription {
+    /// Render the description as a JSON document.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.write_json(&mut out);
+        out
+    }
+
+    fn write_json(&self, out: &mut String) {
+        out.push_str("{\"middlewares\":[");
+        for (i, middleware) in self.middlewares.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            middleware.write_json(out);
+        }
+        out.push_str("],\"fallback\":");
+        out.push_str(&json::string(&self.fallback));
+        out.push('}');
+    }
+
+    fn write_text(&self, fmt: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        for middleware in &self.middlewares {
+            middleware.write_text(fmt, depth)?;
+        }
+        writeln!(fmt, "{:indent$}(fallback) {}", "", self.fallback, indent = depth * 4)
+    }
+}
+
+impl MiddlewareDescription {
+    fn write_json(&self, out: &mut String) {
+        out.push_str("{\"name\":");
+        out.push_str(&json::optional_string(self.name.as_ref()));
+        out.push_str(",\"kind\":");
+        out.push_str(&json::string(&self.kind));
+        out.push_str(",\"detail\":");
+        out.push_str(&json::optional_string(self.detail.as_ref()));
+        out.push_str(",\"path\":");
+        out.push_str(&json::optional_string(self.path.as_ref()));
+        out.push_str(",\"terminal\":");
+        out.push_str(if self.terminal { "true" } else { "false" });
+        out.push_str(",\"fallthrough\":");
+        out.push_str(if self.fallthrough { "true" } else { "false" });
+        out.push_str(",\"branches\":[");
+        for (i, branch) in self.branches.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push_str("{\"label\":");
+            out.push_str(&json::optional_string(branch.label.as_ref()));
+            out.push_str(",\"pipeline\":");
+            branch.pipeline.write_json(out);
+            out.push('}');
+        }
+        out.push_str("]}");
+    }
+
+    fn write_text(&self, fmt: &mut fmt::Formatter, depth: usize) -> fmt::Result {
+        write!(fmt, "{:indent$}", "", indent = depth * 4)?;
+        if let Some(ref name) = self.name {
+            write!(fmt, "{}: ", name)?;
+        }
+        write!(fmt, "{}", self.kind)?;
+        if let Some(ref detail) = self.detail {
+            write!(fmt, " {}", detail)?;
+        }
+        writeln!(fmt)?;
+        for branch in &self.branches {
+            if let Some(ref label) = branch.label {
+                writeln!(fmt, "{:indent$}[{}]", "", label, indent = (depth + 1) * 4)?;
+                branch.pipeline.write_text(fmt, depth + 2)?;
+            }
+            else {
+                branch.pipeline.write_text(fmt, depth + 1)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PipelineDescription {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        self.write_text(fmt, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::short_type_name;
+
+    #[test]
+    fn short_type_name_strips_paths() {
+        let name = short_type_name("iron_pipeline::middleware::handle::Handle<app::main::{{closure}}>");
+        assert_eq!(name, "Handle<{{closure}}>");
+    }
+
+    #[test]
+    fn short_type_name_multiple_parameters() {
+        let name = short_type_name("a::Pair<b::One, c::d::Two<&e::Three>>");
+        assert_eq!(name, "Pair<One, Two<&Three>>");
+    }
+}
@@ -0,0 +1,45 @@
+//! Minimal helpers for writing JSON documents.
+
+use std::fmt::Write;
+
+/// Quote and escape a string as a JSON string literal.
+pub fn string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"'  => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            },
+            c => out.push(c)
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Quote and escape a string as a JSON string literal, or `null`.
+pub fn optional_string<S>(value: Option<S>) -> String
+    where S: AsRef<str>
+{
+    match value {
+        Some(value) => string(value.as_ref()),
+        None        => "null".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::string;
+
+    #[test]
+    fn string_escapes_special_characters() {
+        assert_eq!(string("say \"hi\"\\\n\u{1}"), "\"say \\\"hi\\\"\\\\\\n\\u0001\"");
+    }
+}
@@ -67,30 +67,73 @@
 //! extern crate iron_pipeline;
 //! ```
 
+#[cfg(feature = "brotli")]
+extern crate brotli;
+extern crate flate2;
 extern crate iron;
+#[macro_use]
+extern crate log;
 extern crate url;
 
 pub mod middleware;
+pub mod predicate;
+pub mod describe;
+pub mod compiled;
+pub mod swappable;
+
+mod json;
 
 /// Includes the Pipeline type and all middleware types in the `middleware` module.
 pub mod prelude {
-    pub use Pipeline;
+    pub use {Pipeline, Fallback};
+    pub use compiled::CompiledPipeline;
+    pub use swappable::SwappablePipeline;
+    pub use middleware::access_log::AccessLog;
+    pub use middleware::catch_panic::CatchPanic;
+    pub use middleware::compress::Compress;
+    pub use middleware::cors::Cors;
+    pub use middleware::decompress::Decompress;
+    pub use middleware::error_boundary::ErrorBoundary;
     pub use middleware::fork::Fork;
     pub use middleware::handle::{Handle, HandleNext};
+    pub use middleware::problem::Problems;
+    pub use middleware::request_id::AssignRequestId;
+    pub use middleware::router::Router;
+    pub use middleware::switch::Switch;
+    pub use middleware::version::Versioned;
+    pub use middleware::when::When;
 }
 
 use std::error;
 use std::fmt;
 
 use iron::status;
+use iron::method::Method;
 use iron::prelude::*;
 use iron::middleware::Handler;
 
+use describe::{PipelineDescription, MiddlewareDescription};
+use middleware::fork::{ForkTrail, OriginalUrl};
+use middleware::when::When;
+use predicate::Predicate;
+
 /// Trait which defines middleware within a pipeline.
 /// Implementors of this trait must call `next.handle(...)` in order to pass
 /// control to the next middleware in the pipeline.
 pub trait Middleware: Send + Sync {
     fn process(&self, req: &mut Request, next: PipelineNext) -> IronResult<Response>;
+
+    /// Describe this middleware for `Pipeline::describe`.
+    /// Defaults to a description containing the type name of the middleware.
+    fn describe(&self) -> MiddlewareDescription {
+        MiddlewareDescription::of::<Self>()
+    }
+
+    /// Get mutable access to a nested pipeline, identified by the label of the
+    /// branch in this middleware's description. Defaults to `None`.
+    fn branch_mut(&mut self, _label: Option<&str>) -> Option<&mut Pipeline> {
+        None
+    }
 }
 
 // NOTE: Implement Middleware for all types which also implement Handler
@@ -102,6 +145,10 @@ impl<T> Middleware for T
     fn process(&self, req: &mut Request, _: PipelineNext) -> IronResult<Response> {
         self.handle(req)
     }
+
+    fn describe(&self) -> MiddlewareDescription {
+        MiddlewareDescription::of::<Self>().terminal()
+    }
 }
 
 /// Iron middleware for implementing a simple forward-only pipeline.
@@ -134,72 +181,438 @@ impl<T> Middleware for T
 /// # }
 /// ```
 pub struct Pipeline {
-    middlewares: Vec<Box<Middleware>>
+    middlewares: Vec<Entry>,
+    fallback: Fallback
+}
+
+/// A middleware registered with a pipeline, and its (optional) name.
+struct Entry {
+    name: Option<String>,
+    middleware: Box<dyn Middleware>
+}
+
+/// Determines how a pipeline completes a request which has been passed
+/// along by every registered middleware.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate iron;
+/// # extern crate iron_pipeline;
+/// # fn main() {
+/// # use iron::prelude::*;
+/// # use iron::status;
+/// # use iron_pipeline::prelude::*;
+/// let mut pipeline = Pipeline::new();
+/// pipeline.add(Fork::when_path("/api", |api| {
+///     // Unhandled API requests are answered with a 404
+///     api.set_fallback(Fallback::Status(status::NotFound));
+/// }));
+/// pipeline.set_fallback(Fallback::with(Handle(|_| {
+///     Ok(Response::with((status::NotFound, "Not Found")))
+/// })));
+/// # }
+/// ```
+pub enum Fallback {
+    /// Raise an `Error::NoHandler` with the given status. The error propagates
+    /// out of the pipeline, and out of any parent pipeline.
+    ///
+    /// This is the default, with a status of `500 Internal Server Error`.
+    Raise(status::Status),
+    /// Respond with an empty response with the given status.
+    Status(status::Status),
+    /// Delegate to the given middleware. If the fallback middleware invokes
+    /// the next middleware an `Error::NoHandler` is raised.
+    Middleware(Box<dyn Middleware>)
+}
+
+impl Fallback {
+    /// Construct a fallback which delegates to the given middleware.
+    pub fn with<M>(middleware: M) -> Fallback
+        where M: Middleware + 'static
+    {
+        Fallback::Middleware(Box::new(middleware))
+    }
+}
+
+impl Fallback {
+    fn describe(&self) -> String {
+        match *self {
+            Fallback::Raise(status)              => format!("raise {}", status),
+            Fallback::Status(status)             => format!("status {}", status),
+            Fallback::Middleware(ref middleware) => format!("middleware {}", middleware.describe().kind)
+        }
+    }
+}
+
+impl Default for Fallback {
+    fn default() -> Fallback {
+        Fallback::Raise(status::InternalServerError)
+    }
 }
 
+/// Continuation invoked when a sub pipeline runs out of middleware,
+/// in place of the sub pipeline's fallback.
+type Exhausted<'a> = dyn Fn(&mut Request) -> IronResult<Response> + 'a;
+
 /// Handle used to invoke the next handler in a pipeline
-pub struct PipelineNext<'a>(&'a Pipeline, usize);
+pub struct PipelineNext<'a> {
+    pipeline: &'a Pipeline,
+    index: usize,
+    exhausted: Option<&'a Exhausted<'a>>
+}
 
 impl<'a> PipelineNext<'a> {
     pub fn process(&self, req: &mut Request) -> IronResult<Response> {
-        let PipelineNext(pipeline, idx) = *self;
-        pipeline.invoke_handler(idx, req)
+        self.pipeline.invoke_handler(self.index, req, self.exhausted)
     }
 }
 
 impl Pipeline {
     /// Construct a new, empty request pipeline.
     pub fn new() -> Pipeline {
-        Pipeline { middlewares: Vec::new() }
+        Pipeline { middlewares: Vec::new(), fallback: Fallback::default() }
     }
 
     /// Append a middleware to the end of the pipeline
     pub fn add<P>(&mut self, handler: P)
         where P: Middleware + 'static
     {
-        self.middlewares.push(Box::new(handler));
+        self.middlewares.push(Entry { name: None, middleware: Box::new(handler) });
+    }
+
+    /// Append a named middleware to the end of the pipeline.
+    /// The name is included in the output of `describe`.
+    pub fn add_named<N, P>(&mut self, name: N, handler: P)
+        where N: Into<String>,
+              P: Middleware + 'static
+    {
+        self.middlewares.push(Entry { name: Some(name.into()), middleware: Box::new(handler) });
+    }
+
+    /// Append a middleware to the end of the pipeline, which is only invoked
+    /// for requests which match the predicate. Other requests skip straight
+    /// to the next middleware. See `When`.
+    pub fn add_when<P, M>(&mut self, predicate: P, handler: M)
+        where P: Predicate + 'static,
+              M: Middleware + 'static
+    {
+        self.add(When(predicate, handler));
+    }
+
+    /// Set the fallback used when a request is passed along by every middleware
+    /// in the pipeline. Defaults to `Fallback::Raise(status::InternalServerError)`.
+    pub fn set_fallback(&mut self, fallback: Fallback) {
+        self.fallback = fallback;
+    }
+
+    /// Insert a middleware immediately before the selected middleware.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate iron;
+    /// # extern crate iron_pipeline;
+    /// # fn main() {
+    /// # use iron::prelude::*;
+    /// # use iron_pipeline::prelude::*;
+    /// let mut pipeline = Pipeline::new();
+    /// pipeline.add_named("app", Handle(|_| Ok(Response::with("Hello from iron-pipeline"))));
+    ///
+    /// // Insert by name...
+    /// pipeline.insert_before("app", HandleNext(|req, next| next.process(req))).unwrap();
+    /// // ...or by index
+    /// pipeline.insert_before(0, HandleNext(|req, next| next.process(req))).unwrap();
+    /// # }
+    /// ```
+    pub fn insert_before<S, P>(&mut self, at: S, handler: P) -> Result<(), EditError>
+        where S: Into<Selector>,
+              P: Middleware + 'static
+    {
+        let index = self.position(at.into())?;
+        self.middlewares.insert(index, Entry { name: None, middleware: Box::new(handler) });
+        Ok(())
+    }
+
+    /// Insert a named middleware immediately before the selected middleware.
+    pub fn insert_named_before<S, N, P>(&mut self, at: S, name: N, handler: P) -> Result<(), EditError>
+        where S: Into<Selector>,
+              N: Into<String>,
+              P: Middleware + 'static
+    {
+        let index = self.position(at.into())?;
+        self.middlewares.insert(index, Entry { name: Some(name.into()), middleware: Box::new(handler) });
+        Ok(())
+    }
+
+    /// Insert a middleware immediately after the selected middleware.
+    pub fn insert_after<S, P>(&mut self, at: S, handler: P) -> Result<(), EditError>
+        where S: Into<Selector>,
+              P: Middleware + 'static
+    {
+        let index = self.position(at.into())?;
+        self.middlewares.insert(index + 1, Entry { name: None, middleware: Box::new(handler) });
+        Ok(())
+    }
+
+    /// Insert a named middleware immediately after the selected middleware.
+    pub fn insert_named_after<S, N, P>(&mut self, at: S, name: N, handler: P) -> Result<(), EditError>
+        where S: Into<Selector>,
+              N: Into<String>,
+              P: Middleware + 'static
+    {
+        let index = self.position(at.into())?;
+        self.middlewares.insert(index + 1, Entry { name: Some(name.into()), middleware: Box::new(handler) });
+        Ok(())
+    }
+
+    /// Replace the selected middleware. The replacement keeps the name of the original.
+    pub fn replace<S, P>(&mut self, at: S, handler: P) -> Result<(), EditError>
+        where S: Into<Selector>,
+              P: Middleware + 'static
+    {
+        let index = self.position(at.into())?;
+        self.middlewares[index].middleware = Box::new(handler);
+        Ok(())
+    }
+
+    /// Remove the selected middleware.
+    pub fn remove<S>(&mut self, at: S) -> Result<(), EditError>
+        where S: Into<Selector>
+    {
+        let index = self.position(at.into())?;
+        self.middlewares.remove(index);
+        Ok(())
+    }
+
+    /// Get mutable access to the pipeline nested within the selected middleware,
+    /// e.g. the sub pipeline of a `Fork`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate iron;
+    /// # extern crate iron_pipeline;
+    /// # fn main() {
+    /// # use iron::prelude::*;
+    /// # use iron_pipeline::prelude::*;
+    /// let mut pipeline = Pipeline::new();
+    /// pipeline.add_named("api", Fork::when_path("/api", |api| {
+    ///     api.add_named("app", Handle(|_| Ok(Response::with("Hello from iron-pipeline"))));
+    /// }));
+    ///
+    /// pipeline.nested_mut("api").unwrap()
+    ///     .insert_before("app", HandleNext(|req, next| next.process(req))).unwrap();
+    /// # }
+    /// ```
+    pub fn nested_mut<S>(&mut self, at: S) -> Result<&mut Pipeline, EditError>
+        where S: Into<Selector>
+    {
+        self.branch_mut(at, None)
+    }
+
+    /// Get mutable access to the labelled branch of the selected middleware,
+    /// for middleware which holds more than one nested pipeline.
+    pub fn branch_mut<S>(&mut self, at: S, label: Option<&str>) -> Result<&mut Pipeline, EditError>
+        where S: Into<Selector>
+    {
+        let selector = at.into();
+        let index = self.position(selector.clone())?;
+        self.middlewares[index].middleware.branch_mut(label)
+            .ok_or(EditError::NoBranch(selector, label.map(|l| l.to_string())))
+    }
+
+    /// Find the index of the selected middleware
+    fn position(&self, selector: Selector) -> Result<usize, EditError> {
+        let index = match selector {
+            Selector::Index(index) => {
+                if index < self.middlewares.len() { Some(index) } else { None }
+            },
+            Selector::Name(ref name) => {
+                self.middlewares.iter().position(|entry| entry.name.as_ref() == Some(name))
+            }
+        };
+        index.ok_or(EditError::NotFound(selector))
+    }
+
+    /// Describe the topology of the pipeline: the name and kind of each middleware,
+    /// any fork predicates, and any nested pipelines.
+    pub fn describe(&self) -> PipelineDescription {
+        PipelineDescription {
+            middlewares: self.middlewares.iter()
+                .map(|entry| MiddlewareDescription { name: entry.name.clone(), ..entry.middleware.describe() })
+                .collect(),
+            fallback: self.fallback.describe()
+        }
+    }
+
+    /// Invoke the pipeline, calling `exhausted` instead of the fallback
+    /// if the request is passed along by every middleware.
+    fn handle_or_else(&self, req: &mut Request, exhausted: &Exhausted) -> IronResult<Response> {
+        self.invoke_handler(0, req, Some(exhausted))
     }
 
     /// Invoke the pipeline handler at the given index. The handler is provided
     /// With a PipelineNext callback which will invoke the next handler in the
     /// pipeline (at position index + 1).
-    fn invoke_handler(&self, index: usize, req: &mut Request) -> IronResult<Response> {
+    fn invoke_handler<'a>(&'a self, index: usize, req: &mut Request, exhausted: Option<&'a Exhausted<'a>>) -> IronResult<Response> {
 
         // Locate the next handler and invoke it
-        if let Some(middleware) = self.middlewares.get(index) {
-            return middleware.process(req, PipelineNext(self, index + 1));
+        if let Some(entry) = self.middlewares.get(index) {
+            return entry.middleware.process(req, PipelineNext { pipeline: self, index: index + 1, exhausted });
         }
 
-        // No more middlewares? Return an error to the client
-        Err(IronError::new(Error::NoHandler, status::InternalServerError))
+        // No more middlewares? Hand the request back to the parent pipeline, if any
+        if let Some(exhausted) = exhausted {
+            return exhausted(req);
+        }
+
+        // No more middlewares? Invoke the fallback, unless the fallback itself
+        // invoked the next middleware
+        if index == self.middlewares.len() {
+            match self.fallback {
+                Fallback::Raise(status) => {
+                    return Err(IronError::new(Error::no_handler(req), status));
+                },
+                Fallback::Status(status) => {
+                    return Ok(Response::with(status));
+                },
+                Fallback::Middleware(ref middleware) => {
+                    return middleware.process(req, PipelineNext { pipeline: self, index: index + 1, exhausted: None });
+                }
+            }
+        }
+
+        Err(IronError::new(Error::no_handler(req), status::InternalServerError))
+    }
+}
+
+impl Default for Pipeline {
+    fn default() -> Pipeline {
+        Pipeline::new()
     }
 }
 
 impl Handler for Pipeline {
     /// Invokes the request pipeline
     fn handle(&self, req: &mut Request) -> IronResult<Response> {
-        self.invoke_handler(0, req)
+        self.invoke_handler(0, req, None)
     }
 }
 
 /// Errors which may be raised by the Pipeline itself
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Error {
     /// Raised if there are no further middlewares in the pipeline
     /// available to handle the request
-    NoHandler
+    NoHandler {
+        /// The request method
+        method: Method,
+        /// The original (root) request URL
+        url: iron::Url,
+        /// Descriptions of each fork the request passed through, outermost first
+        forks: Vec<String>
+    }
+}
+
+impl Error {
+    fn no_handler(req: &Request) -> Error {
+        Error::NoHandler {
+            method: req.method.clone(),
+            url: req.extensions.get::<OriginalUrl>().unwrap_or(&req.url).clone(),
+            forks: req.extensions.get::<ForkTrail>().cloned().unwrap_or_default()
+        }
+    }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        write!(fmt, "Pipeline error ({})", error::Error::description(self))
+        match *self {
+            Error::NoHandler { ref method, ref url, ref forks } => {
+                write!(fmt, "Pipeline error (Missing handler): {} {}", method, url)?;
+                if !forks.is_empty() {
+                    write!(fmt, " via {}", forks.join(" > "))?;
+                }
+                Ok(())
+            }
+        }
     }
 }
 
 impl error::Error for Error {
     fn description(&self) -> &'static str {
         match *self {
-            Error::NoHandler => "Missing handler"
+            Error::NoHandler { .. } => "Missing handler"
+        }
+    }
+}
+
+/// Identifies a middleware within a pipeline, either by its position
+/// or by the name it was registered with.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selector {
+    Index(usize),
+    Name(String)
+}
+
+impl From<usize> for Selector {
+    fn from(index: usize) -> Selector {
+        Selector::Index(index)
+    }
+}
+
+impl<'a> From<&'a str> for Selector {
+    fn from(name: &'a str) -> Selector {
+        Selector::Name(name.to_string())
+    }
+}
+
+impl From<String> for Selector {
+    fn from(name: String) -> Selector {
+        Selector::Name(name)
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Selector::Index(index)   => write!(fmt, "#{}", index),
+            Selector::Name(ref name) => write!(fmt, "\"{}\"", name)
+        }
+    }
+}
+
+/// Errors which may be raised when editing a Pipeline
+#[derive(Debug, Clone, PartialEq)]
+pub enum EditError {
+    /// Raised if no middleware matches the selector
+    NotFound(Selector),
+    /// Raised if the selected middleware does not have a nested pipeline
+    /// with the given label
+    NoBranch(Selector, Option<String>)
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EditError::NotFound(ref selector) => {
+                write!(fmt, "Pipeline edit error: no middleware matches {}", selector)
+            },
+            EditError::NoBranch(ref selector, None) => {
+                write!(fmt, "Pipeline edit error: middleware {} has no nested pipeline", selector)
+            },
+            EditError::NoBranch(ref selector, Some(ref label)) => {
+                write!(fmt, "Pipeline edit error: middleware {} has no nested pipeline [{}]", selector, label)
+            }
+        }
+    }
+}
+
+impl error::Error for EditError {
+    fn description(&self) -> &'static str {
+        match *self {
+            EditError::NotFound(_)    => "Middleware not found",
+            EditError::NoBranch(_, _) => "Nested pipeline not found"
         }
     }
 }
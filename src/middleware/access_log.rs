@@ -0,0 +1,372 @@
+use iron::prelude::*;
+use iron::headers::ContentLength;
+
+use std::fmt::Write as FmtWrite;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use {Middleware, PipelineNext, json};
+use describe::MiddlewareDescription;
+use middleware::request_id::RequestId;
+
+/// Middleware which writes a line to an access log for each request, once
+/// the middleware after it have produced a response (or an error).
+///
+/// # Examples
+/// Log requests to a file in Apache "Combined" format, keeping 5 rotated files of 10 MiB:
+///
+/// ```rust,no_run
+/// # extern crate iron;
+/// # extern crate iron_pipeline;
+/// # use iron::prelude::*;
+/// # use iron_pipeline::prelude::*;
+/// use iron_pipeline::middleware::access_log::{AccessLog, LogFormat, RotatingFile};
+///
+/// # fn main() {
+/// # let mut pipeline = Pipeline::new();
+/// let file = RotatingFile::new("/var/log/app/access.log", 10 * 1024 * 1024, 5).unwrap();
+/// pipeline.add(AccessLog::new(LogFormat::Combined, file));
+/// # }
+/// ```
+pub struct AccessLog {
+    format: LogFormat,
+    sink: Mutex<Box<dyn Write + Send>>
+}
+
+/// The format of each line written to an access log.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LogFormat {
+    /// Apache Common Log Format, e.g.
+    /// `127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] "GET /index.html HTTP/1.1" 200 2326`
+    Common,
+    /// Apache Combined Log Format, which adds the `Referer` and `User-Agent` to `Common`
+    Combined,
+    /// A JSON document per line
+    Json,
+    /// A custom template, in which any of the following placeholders are replaced:
+    /// `{time}`, `{remote_addr}`, `{method}`, `{path}`, `{protocol}`, `{status}`, `{bytes}`,
+    /// `{duration_ms}`, `{user_agent}`, `{referer}` and `{request_id}`.
+    /// Missing values are written as `-`.
+    Template(String)
+}
+
+/// The details of a request recorded in an access log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LogEntry {
+    /// When the request was received
+    pub time: SystemTime,
+    /// The address of the client
+    pub remote_addr: SocketAddr,
+    /// The request method
+    pub method: String,
+    /// The request path and query string, e.g. `/users?page=2`
+    pub path: String,
+    /// The HTTP version, e.g. `HTTP/1.1`
+    pub protocol: String,
+    /// The response status code, if set
+    pub status: Option<u16>,
+    /// The size of the response body in bytes, if known
+    pub bytes: Option<u64>,
+    /// The time taken to produce the response
+    pub duration: Duration,
+    /// The `User-Agent` request header
+    pub user_agent: Option<String>,
+    /// The `Referer` request header
+    pub referer: Option<String>,
+    /// The ID assigned by `AssignRequestId`, or else the `X-Request-Id` request header
+    pub request_id: Option<String>
+}
+
+impl LogEntry {
+    fn duration_ms(&self) -> String {
+        format!("{:.3}", self.duration.as_secs() as f64 * 1e3 + f64::from(self.duration.subsec_nanos()) / 1e6)
+    }
+
+    /// Render the entry in the given format (without a trailing newline).
+    pub fn format(&self, format: &LogFormat) -> String {
+        let or_dash = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+        match *format {
+            LogFormat::Common => self.common(),
+            LogFormat::Combined => {
+                format!("{} \"{}\" \"{}\"", self.common(), or_dash(self.referer.clone()), or_dash(self.user_agent.clone()))
+            },
+            LogFormat::Json => self.json(),
+            LogFormat::Template(ref template) => {
+                // NOTE: Substitute in a single pass, so values containing placeholders are left alone
+                let mut out = String::new();
+                let mut rest = template.as_str();
+                while let Some(start) = rest.find('{') {
+                    out.push_str(&rest[..start]);
+                    let placeholder = rest[start..].find('}').map(|end| &rest[start..start + end + 1]);
+                    let value = placeholder.and_then(|placeholder| self.placeholder(placeholder));
+                    match (placeholder, value) {
+                        (Some(placeholder), Some(value)) => {
+                            out.push_str(&or_dash(value));
+                            rest = &rest[start + placeholder.len()..];
+                        },
+                        _ => {
+                            out.push('{');
+                            rest = &rest[start + 1..];
+                        }
+                    }
+                }
+                out.push_str(rest);
+                out
+            }
+        }
+    }
+
+    /// Get the value for a template placeholder, or `None` if the placeholder is unknown.
+    fn placeholder(&self, placeholder: &str) -> Option<Option<String>> {
+        let value = match placeholder {
+            "{time}"        => Some(rfc3339(self.time)),
+            "{remote_addr}" => Some(self.remote_addr.to_string()),
+            "{method}"      => Some(self.method.clone()),
+            "{path}"        => Some(self.path.clone()),
+            "{protocol}"    => Some(self.protocol.clone()),
+            "{status}"      => self.status.map(|s| s.to_string()),
+            "{bytes}"       => self.bytes.map(|b| b.to_string()),
+            "{duration_ms}" => Some(self.duration_ms()),
+            "{user_agent}"  => self.user_agent.clone(),
+            "{referer}"     => self.referer.clone(),
+            "{request_id}"  => self.request_id.clone(),
+            _               => return None
+        };
+        Some(value)
+    }
+
+    fn common(&self) -> String {
+        format!("{} - - [{}] \"{} {} {}\" {} {}",
+            self.remote_addr.ip(),
+            common_log_time(self.time),
+            self.method, self.path, self.protocol,
+            self.status.map_or("-".to_string(), |s| s.to_string()),
+            self.bytes.map_or("-".to_string(), |b| b.to_string()))
+    }
+
+    fn json(&self) -> String {
+        let mut out = String::new();
+        out.push_str("{\"time\":");
+        out.push_str(&json::string(&rfc3339(self.time)));
+        out.push_str(",\"remote_addr\":");
+        out.push_str(&json::string(&self.remote_addr.to_string()));
+        out.push_str(",\"method\":");
+        out.push_str(&json::string(&self.method));
+        out.push_str(",\"path\":");
+        out.push_str(&json::string(&self.path));
+        out.push_str(",\"protocol\":");
+        out.push_str(&json::string(&self.protocol));
+        let _ = write!(out, ",\"status\":{}", self.status.map_or("null".to_string(), |s| s.to_string()));
+        let _ = write!(out, ",\"bytes\":{}", self.bytes.map_or("null".to_string(), |b| b.to_string()));
+        let _ = write!(out, ",\"duration_ms\":{}", self.duration_ms());
+        out.push_str(",\"user_agent\":");
+        out.push_str(&json::optional_string(self.user_agent.as_ref()));
+        out.push_str(",\"referer\":");
+        out.push_str(&json::optional_string(self.referer.as_ref()));
+        out.push_str(",\"request_id\":");
+        out.push_str(&json::optional_string(self.request_id.as_ref()));
+        out.push('}');
+        out
+    }
+}
+
+/// Split a time into its UTC date and time components:
+/// (year, month, day, hour, minute, second).
+fn utc(time: SystemTime) -> (i64, u32, u32, u32, u32, u32) {
+    let secs = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0);
+    let (days, rem) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+
+    // Convert days since the epoch to a civil date (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day, (rem / 3600) as u32, (rem % 3600 / 60) as u32, (rem % 60) as u32)
+}
+
+/// Format a time as in the Common Log Format, e.g. `10/Oct/2000:13:55:36 +0000`.
+fn common_log_time(time: SystemTime) -> String {
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+    let (year, month, day, hour, minute, second) = utc(time);
+    format!("{:02}/{}/{}:{:02}:{:02}:{:02} +0000", day, MONTHS[month as usize - 1], year, hour, minute, second)
+}
+
+/// Format a time as RFC 3339, e.g. `2000-10-10T13:55:36Z`.
+fn rfc3339(time: SystemTime) -> String {
+    let (year, month, day, hour, minute, second) = utc(time);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, hour, minute, second)
+}
+
+fn header(req: &Request, name: &str) -> Option<String> {
+    req.headers.get_raw(name)
+        .and_then(|values| values.first())
+        .map(|value| String::from_utf8_lossy(value).into_owned())
+}
+
+impl AccessLog {
+    /// Construct a new access log which writes lines in the given format to the sink.
+    pub fn new<W>(format: LogFormat, sink: W) -> AccessLog
+        where W: Write + Send + 'static
+    {
+        AccessLog { format, sink: Mutex::new(Box::new(sink)) }
+    }
+
+    /// Construct a new access log which writes lines in the given format to stdout.
+    pub fn stdout(format: LogFormat) -> AccessLog {
+        AccessLog::new(format, io::stdout())
+    }
+
+    fn write(&self, entry: &LogEntry) {
+        let mut line = entry.format(&self.format);
+        line.push('\n');
+        let mut sink = self.sink.lock().unwrap_or_else(|err| err.into_inner());
+        if let Err(err) = sink.write_all(line.as_bytes()).and_then(|_| sink.flush()) {
+            warn!("Failed to write to access log: {}", err);
+        }
+    }
+}
+
+impl Middleware for AccessLog {
+    fn process(&self, req: &mut Request, next: PipelineNext) -> IronResult<Response> {
+        let time = SystemTime::now();
+        let start = Instant::now();
+
+        // NOTE: Record the URL before any fork modifies it
+        let path = match req.url.query() {
+            Some(query) => format!("/{}?{}", req.url.path().join("/"), query),
+            None        => format!("/{}", req.url.path().join("/"))
+        };
+
+        let result = next.process(req);
+
+        let response = match result {
+            Ok(ref response) => response,
+            Err(ref err)     => &err.response
+        };
+        let entry = LogEntry {
+            time,
+            remote_addr: req.remote_addr,
+            method: req.method.to_string(),
+            path,
+            protocol: req.version.to_string(),
+            status: response.status.map(|s| s.to_u16()),
+            bytes: response.headers.get::<ContentLength>().map(|l| l.0),
+            duration: start.elapsed(),
+            user_agent: header(req, "User-Agent"),
+            referer: header(req, "Referer"),
+            request_id: req.extensions.get::<RequestId>().cloned().or_else(|| header(req, "X-Request-Id"))
+        };
+        self.write(&entry);
+        result
+    }
+
+    fn describe(&self) -> MiddlewareDescription {
+        let format = match self.format {
+            LogFormat::Common          => "common".to_string(),
+            LogFormat::Combined        => "combined".to_string(),
+            LogFormat::Json            => "json".to_string(),
+            LogFormat::Template(ref t) => format!("template({})", t)
+        };
+        MiddlewareDescription::new("AccessLog").detail(format)
+    }
+}
+
+/// A log file which is rotated when it grows larger than a maximum size.
+///
+/// When rotated, `access.log` is renamed to `access.log.1`, `access.log.1` to
+/// `access.log.2` and so on, and the oldest file is deleted.
+pub struct RotatingFile {
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    file: File,
+    written: u64
+}
+
+impl RotatingFile {
+    /// Open (or create) the log file at `path`, which is rotated once it grows
+    /// larger than `max_bytes`, keeping up to `keep` rotated files.
+    pub fn new<P>(path: P, max_bytes: u64, keep: usize) -> io::Result<RotatingFile>
+        where P: AsRef<Path>
+    {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(RotatingFile { path, max_bytes, keep, file, written })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        PathBuf::from(path)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.keep == 0 {
+            fs::remove_file(&self.path)?;
+        }
+        else {
+            let oldest = self.rotated_path(self.keep);
+            if oldest.exists() {
+                fs::remove_file(&oldest)?;
+            }
+            for index in (1..self.keep).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    fs::rename(&from, self.rotated_path(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written > 0 && self.written + buf.len() as u64 > self.max_bytes {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{common_log_time, rfc3339};
+
+    #[test]
+    fn format_times() {
+        let time = UNIX_EPOCH + Duration::from_secs(971_186_136);
+        assert_eq!(common_log_time(time), "10/Oct/2000:13:55:36 +0000");
+        assert_eq!(rfc3339(time), "2000-10-10T13:55:36Z");
+    }
+
+    #[test]
+    fn format_leap_day() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_709_210_096);
+        assert_eq!(rfc3339(time), "2024-02-29T12:34:56Z");
+    }
+}
@@ -0,0 +1,141 @@
+use iron;
+use iron::prelude::*;
+use iron::status;
+
+use std::any::Any;
+use std::error;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+use {Middleware, PipelineNext};
+use describe::MiddlewareDescription;
+use middleware::fork::OriginalUrl;
+use middleware::request_id::RequestId;
+
+/// Function which produces the response sent when a panic is caught.
+type PanicResponseFn = dyn Fn(&Request, &Panicked) -> Response + Send + Sync;
+
+/// Middleware which catches any panic raised by the middleware after it, and
+/// turns it into an error response instead of dropping the connection.
+///
+/// The panic message and request line are logged (via the `log` crate) at the
+/// error level. The request fails with a `Panicked` error, which is also stored in
+/// the `CaughtPanic` request extension, so that an outer middleware (such as
+/// `ErrorBoundary`) may render it.
+///
+/// The log message includes the ID assigned by `AssignRequestId`, if any.
+///
+/// The response defaults to `500 Internal Server Error`.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate iron;
+/// # extern crate iron_pipeline;
+/// # use iron::prelude::*;
+/// # use iron::status;
+/// # use iron_pipeline::prelude::*;
+/// # fn main() {
+/// # let mut pipeline = Pipeline::new();
+/// pipeline.add(CatchPanic::new()
+///     .response(|_, _| Response::with((status::InternalServerError, "Something went wrong"))));
+/// pipeline.add(Handle(|_| panic!("Oops")));
+/// # }
+/// ```
+///
+/// # Notes
+/// Panics are only caught if the crate is compiled with `panic = "unwind"` (the default).
+/// The default panic hook still prints each panic to stderr.
+pub struct CatchPanic {
+    response: Box<PanicResponseFn>
+}
+
+/// Error raised when `CatchPanic` catches a panic.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Panicked {
+    /// The panic message, if the panic payload was a string
+    pub message: Option<String>,
+    /// The request line, e.g. `GET http://localhost/api`
+    pub request: String
+}
+
+impl fmt::Display for Panicked {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        #[allow(deprecated)]
+        let description = error::Error::description(self);
+        match self.message {
+            Some(ref message) => write!(fmt, "{} handling {}: {}", description, self.request, message),
+            None              => write!(fmt, "{} handling {}", description, self.request)
+        }
+    }
+}
+
+impl error::Error for Panicked {
+    fn description(&self) -> &'static str {
+        "Panicked"
+    }
+}
+
+/// Request extension containing the panic caught by `CatchPanic`.
+pub struct CaughtPanic;
+impl iron::typemap::Key for CaughtPanic {
+    type Value = Panicked;
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> Option<String> {
+    if let Some(message) = payload.downcast_ref::<&'static str>() {
+        return Some(message.to_string());
+    }
+    payload.downcast_ref::<String>().cloned()
+}
+
+impl CatchPanic {
+    /// Construct a new `CatchPanic` middleware which responds with
+    /// `500 Internal Server Error`.
+    pub fn new() -> CatchPanic {
+        CatchPanic { response: Box::new(|_, _| Response::with(status::InternalServerError)) }
+    }
+
+    /// Set the function used to create the response sent when a panic is caught.
+    pub fn response<F>(mut self, response: F) -> CatchPanic
+        where F: Fn(&Request, &Panicked) -> Response + Send + Sync + 'static
+    {
+        self.response = Box::new(response);
+        self
+    }
+}
+
+impl Default for CatchPanic {
+    fn default() -> CatchPanic {
+        CatchPanic::new()
+    }
+}
+
+impl Middleware for CatchPanic {
+    fn process(&self, req: &mut Request, next: PipelineNext) -> IronResult<Response> {
+        // NOTE: Middleware must not rely on the state of the request after a panic,
+        // other than to report it
+        let payload = match panic::catch_unwind(AssertUnwindSafe(|| next.process(req))) {
+            Ok(result)   => return result,
+            Err(payload) => payload
+        };
+
+        let url = req.extensions.get::<OriginalUrl>().unwrap_or(&req.url).clone();
+        let panicked = Panicked {
+            message: panic_message(&*payload),
+            request: format!("{} {}", req.method, url)
+        };
+        match req.extensions.get::<RequestId>() {
+            Some(id) => error!("{} (request ID {})", panicked, id),
+            None     => error!("{}", panicked)
+        }
+
+        let response = (self.response)(req, &panicked);
+        req.extensions.insert::<CaughtPanic>(panicked.clone());
+        Err(IronError { error: Box::new(panicked), response })
+    }
+
+    fn describe(&self) -> MiddlewareDescription {
+        MiddlewareDescription::new("CatchPanic")
+    }
+}
@@ -0,0 +1,254 @@
+use iron::prelude::*;
+use iron::status;
+use iron::headers::ContentLength;
+
+use flate2;
+use flate2::bufread::ZlibDecoder;
+use flate2::read::{GzDecoder, DeflateDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+#[cfg(feature = "brotli")]
+use brotli;
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+use {Middleware, PipelineNext};
+use describe::MiddlewareDescription;
+
+/// Middleware which compresses the responses returned by the middleware after it,
+/// using the encoding preferred by the request's `Accept-Encoding` header.
+///
+/// Supports `gzip` and `deflate`, and `br` (brotli) when the `brotli` cargo feature
+/// is enabled. Responses which are already encoded, which are smaller than the
+/// minimum size (1 KiB by default) or which have an excluded content type (such as
+/// images, which are usually compressed already) are not compressed.
+///
+/// Note that the response body is buffered in memory in order to compress it.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate iron;
+/// # extern crate iron_pipeline;
+/// # use iron::prelude::*;
+/// # use iron_pipeline::prelude::*;
+/// use iron_pipeline::middleware::compress::Compress;
+///
+/// # fn main() {
+/// # let mut pipeline = Pipeline::new();
+/// pipeline.add(Compress::new().min_size(256).exclude("application/octet-stream"));
+/// pipeline.add(Handle(|_| Ok(Response::with("Hello from iron-pipeline"))));
+/// # }
+/// ```
+pub struct Compress {
+    min_size: usize,
+    excluded: Vec<String>
+}
+
+/// A content encoding supported by `Compress`, in order of preference.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Encoding {
+    #[cfg(feature = "brotli")]
+    Brotli,
+    Gzip,
+    Deflate
+}
+
+impl Encoding {
+    pub(crate) fn all() -> &'static [Encoding] {
+        &[
+            #[cfg(feature = "brotli")]
+            Encoding::Brotli,
+            Encoding::Gzip,
+            Encoding::Deflate
+        ]
+    }
+
+    /// Find the encoding with the given `Content-Encoding` name.
+    pub(crate) fn from_name(name: &str) -> Option<Encoding> {
+        match &name.trim().to_ascii_lowercase()[..] {
+            #[cfg(feature = "brotli")]
+            "br"              => Some(Encoding::Brotli),
+            "gzip" | "x-gzip" => Some(Encoding::Gzip),
+            "deflate"         => Some(Encoding::Deflate),
+            _                 => None
+        }
+    }
+
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            #[cfg(feature = "brotli")]
+            Encoding::Brotli  => "br",
+            Encoding::Gzip    => "gzip",
+            Encoding::Deflate => "deflate"
+        }
+    }
+
+    /// Wrap a reader of encoded data in a decoder.
+    pub(crate) fn decoder<'a, R>(self, reader: R) -> io::Result<Box<dyn Read + 'a>>
+        where R: Read + 'a
+    {
+        Ok(match self {
+            #[cfg(feature = "brotli")]
+            Encoding::Brotli  => Box::new(brotli::Decompressor::new(reader, 4096)),
+            Encoding::Gzip    => Box::new(GzDecoder::new(reader)),
+            Encoding::Deflate => {
+                // NOTE: "deflate" should be zlib wrapped (RFC 9110), but many clients
+                // send raw deflate data instead. Accept both.
+                let mut reader = BufReader::new(reader);
+                let is_zlib = {
+                    let header = reader.fill_buf()?;
+                    header.len() >= 2 && header[0] & 0x0f == 8 && (u16::from(header[0]) << 8 | u16::from(header[1])) % 31 == 0
+                };
+                if is_zlib { Box::new(ZlibDecoder::new(reader)) } else { Box::new(DeflateDecoder::new(reader)) }
+            }
+        })
+    }
+
+    fn encode(self, body: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            #[cfg(feature = "brotli")]
+            Encoding::Brotli => {
+                let mut encoder = brotli::CompressorWriter::new(Vec::new(), 4096, 5, 22);
+                encoder.write_all(body)?;
+                encoder.flush()?;
+                Ok(encoder.into_inner())
+            },
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(body)?;
+                encoder.finish()
+            },
+            Encoding::Deflate => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(body)?;
+                encoder.finish()
+            }
+        }
+    }
+}
+
+/// Choose the supported encoding with the highest quality value in the
+/// `Accept-Encoding` header, preferring encodings in the order of `Encoding::all`.
+fn negotiate(req: &Request) -> Option<Encoding> {
+    let values = req.headers.get_raw("Accept-Encoding")?;
+
+    let mut ranges: Vec<(String, f32)> = Vec::new();
+    for value in values {
+        for range in String::from_utf8_lossy(value).split(',') {
+            let mut parts = range.split(';');
+            let coding = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+            let q = parts
+                .filter_map(|p| p.trim().strip_prefix("q=").and_then(|q| q.parse::<f32>().ok()))
+                .next()
+                .unwrap_or(1.0);
+            if !coding.is_empty() {
+                ranges.push((coding, q));
+            }
+        }
+    }
+
+    let quality = |name: &str| {
+        ranges.iter().find(|r| r.0 == name)
+            .or_else(|| ranges.iter().find(|r| r.0 == "*"))
+            .map_or(0.0, |r| r.1)
+    };
+
+    let mut best: Option<(Encoding, f32)> = None;
+    for &encoding in Encoding::all() {
+        let q = quality(encoding.name());
+        if q > 0.0 && best.is_none_or(|b| q > b.1) {
+            best = Some((encoding, q));
+        }
+    }
+    best.map(|b| b.0)
+}
+
+impl Compress {
+    /// Construct a new compression middleware, which compresses responses of
+    /// at least 1 KiB, excluding images, audio, video and common archive formats.
+    pub fn new() -> Compress {
+        Compress {
+            min_size: 1024,
+            excluded: ["image/", "audio/", "video/", "application/zip", "application/gzip", "application/x-gzip"]
+                .iter().map(|s| s.to_string()).collect()
+        }
+    }
+
+    /// Set the minimum size of the response body, in bytes, to compress.
+    pub fn min_size(mut self, bytes: usize) -> Compress {
+        self.min_size = bytes;
+        self
+    }
+
+    /// Do not compress responses with the content type, e.g. `application/pdf`.
+    /// A content type which ends with `/` excludes all subtypes, e.g. `font/`.
+    pub fn exclude<S>(mut self, content_type: S) -> Compress
+        where S: Into<String>
+    {
+        self.excluded.push(content_type.into().to_ascii_lowercase());
+        self
+    }
+
+    fn is_excluded(&self, response: &Response) -> bool {
+        let content_type = match response.headers.get_raw("Content-Type").and_then(|values| values.first()) {
+            Some(value) => String::from_utf8_lossy(value).to_ascii_lowercase(),
+            None        => return false
+        };
+        let essence = content_type.split(';').next().unwrap_or("").trim();
+        self.excluded.iter().any(|excluded| {
+            if excluded.ends_with('/') { essence.starts_with(&excluded[..]) } else { essence == excluded }
+        })
+    }
+
+    fn compress(&self, req: &Request, response: &mut Response) -> io::Result<()> {
+        let no_content = response.status.is_some_and(|s| s == status::NoContent || s == status::NotModified);
+        if no_content || response.body.is_none() || response.headers.get_raw("Content-Encoding").is_some() || self.is_excluded(response) {
+            return Ok(());
+        }
+
+        // NOTE: The response depends on Accept-Encoding, even when it is not compressed
+        response.headers.append_raw("Vary", b"Accept-Encoding".to_vec());
+
+        let encoding = match negotiate(req) {
+            Some(encoding) => encoding,
+            None           => return Ok(())
+        };
+
+        let mut body = Vec::new();
+        if let Some(mut writer) = response.body.take() {
+            writer.write_body(&mut body)?;
+        }
+
+        if body.len() < self.min_size {
+            response.body = Some(Box::new(body));
+            return Ok(());
+        }
+
+        let encoded = encoding.encode(&body)?;
+        response.headers.set_raw("Content-Encoding", vec![encoding.name().as_bytes().to_vec()]);
+        response.headers.set(ContentLength(encoded.len() as u64));
+        response.body = Some(Box::new(encoded));
+        Ok(())
+    }
+}
+
+impl Default for Compress {
+    fn default() -> Compress {
+        Compress::new()
+    }
+}
+
+impl Middleware for Compress {
+    fn process(&self, req: &mut Request, next: PipelineNext) -> IronResult<Response> {
+        let mut response = next.process(req)?;
+        match self.compress(req, &mut response) {
+            Ok(())   => Ok(response),
+            Err(err) => Err(IronError::new(err, status::InternalServerError))
+        }
+    }
+
+    fn describe(&self) -> MiddlewareDescription {
+        let encodings: Vec<&str> = Encoding::all().iter().map(|e| e.name()).collect();
+        MiddlewareDescription::new("Compress").detail(format!("{} (min {} bytes)", encodings.join(", "), self.min_size))
+    }
+}
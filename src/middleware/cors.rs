@@ -0,0 +1,302 @@
+use iron::prelude::*;
+use iron::status;
+use iron::method::Method;
+
+use {Middleware, PipelineNext};
+use describe::MiddlewareDescription;
+
+/// Middleware which implements Cross-Origin Resource Sharing (CORS).
+///
+/// Preflight requests (`OPTIONS` requests with an `Access-Control-Request-Method`
+/// header) are answered directly, without invoking the next middleware. Other
+/// requests from an allowed origin are passed to the next middleware, and the
+/// `Access-Control-*` headers are added to the response (including error responses).
+/// Requests without an `Origin` header are passed on unchanged.
+///
+/// Unless any origin is allowed without credentials, every response is marked with
+/// `Vary: Origin`, so that caches do not serve a response to a different origin.
+///
+/// Each `Fork` may register its own `Cors` middleware, so that each branch of
+/// a pipeline can have its own policy. Register `Cors` before any `Router`, as
+/// the router otherwise answers `OPTIONS` requests itself.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate iron;
+/// # extern crate iron_pipeline;
+/// # use iron::prelude::*;
+/// # use iron::method::Method;
+/// # use iron_pipeline::prelude::*;
+/// use iron_pipeline::middleware::cors::Cors;
+///
+/// # fn main() {
+/// # let mut pipeline = Pipeline::new();
+/// pipeline.add(Fork::when_path("/api", |api| {
+///     api.add(Cors::new()
+///         .allow_origin("https://app.example.com")
+///         .allow_origin("https://*.preview.example.com")
+///         .allow_methods(vec![Method::Get, Method::Post, Method::Delete])
+///         .allow_headers(vec!["Content-Type", "Authorization"])
+///         .allow_credentials()
+///         .max_age(600));
+///     api.add(Handle(|_| Ok(Response::with("Hello from iron-pipeline"))));
+/// }));
+/// # }
+/// ```
+pub struct Cors {
+    origins: Vec<String>,
+    any_origin: bool,
+    methods: Vec<Method>,
+    headers: Vec<String>,
+    any_header: bool,
+    expose_headers: Vec<String>,
+    credentials: bool,
+    max_age: Option<u32>
+}
+
+/// Match an origin against a pattern, in which each `*` matches one or more
+/// characters other than `/` and `:`, e.g. `https://*.example.com`.
+fn match_origin(pattern: &str, origin: &str) -> bool {
+    let idx = match pattern.find('*') {
+        Some(idx) => idx,
+        None      => return pattern == origin
+    };
+    let (prefix, rest_pattern) = (&pattern[..idx], &pattern[idx + 1..]);
+    if !origin.starts_with(prefix) {
+        return false;
+    }
+    let rest = &origin[prefix.len()..];
+    for (i, c) in rest.char_indices() {
+        if c == '/' || c == ':' {
+            break;
+        }
+        if match_origin(rest_pattern, &rest[i + c.len_utf8()..]) {
+            return true;
+        }
+    }
+    false
+}
+
+impl Cors {
+    /// Construct a new CORS policy which allows no origins, and allows the
+    /// `GET`, `HEAD` and `POST` methods.
+    pub fn new() -> Cors {
+        Cors {
+            origins: Vec::new(),
+            any_origin: false,
+            methods: vec![Method::Get, Method::Head, Method::Post],
+            headers: Vec::new(),
+            any_header: false,
+            expose_headers: Vec::new(),
+            credentials: false,
+            max_age: None
+        }
+    }
+
+    /// Allow requests from the origin, e.g. `https://example.com`. The origin may
+    /// contain `*` wildcards, each of which match part of a hostname, e.g. `https://*.example.com`.
+    pub fn allow_origin<S>(mut self, origin: S) -> Cors
+        where S: Into<String>
+    {
+        self.origins.push(origin.into().to_ascii_lowercase());
+        self
+    }
+
+    /// Allow requests from any origin.
+    pub fn allow_any_origin(mut self) -> Cors {
+        self.any_origin = true;
+        self
+    }
+
+    /// Set the methods which may be used by cross-origin requests.
+    pub fn allow_methods(mut self, methods: Vec<Method>) -> Cors {
+        self.methods = methods;
+        self
+    }
+
+    /// Set the request headers which may be sent by cross-origin requests.
+    pub fn allow_headers<S>(mut self, headers: Vec<S>) -> Cors
+        where S: Into<String>
+    {
+        self.headers = headers.into_iter().map(|h| h.into()).collect();
+        self
+    }
+
+    /// Allow cross-origin requests to send any request header.
+    pub fn allow_any_header(mut self) -> Cors {
+        self.any_header = true;
+        self
+    }
+
+    /// Set the response headers which may be read by cross-origin requests.
+    pub fn expose_headers<S>(mut self, headers: Vec<S>) -> Cors
+        where S: Into<String>
+    {
+        self.expose_headers = headers.into_iter().map(|h| h.into()).collect();
+        self
+    }
+
+    /// Allow cross-origin requests to include credentials, such as cookies.
+    pub fn allow_credentials(mut self) -> Cors {
+        self.credentials = true;
+        self
+    }
+
+    /// Set the number of seconds for which browsers may cache the result of a preflight request.
+    pub fn max_age(mut self, seconds: u32) -> Cors {
+        self.max_age = Some(seconds);
+        self
+    }
+
+    fn is_allowed_origin(&self, origin: &str) -> bool {
+        let origin = origin.to_ascii_lowercase();
+        self.any_origin || self.origins.iter().any(|pattern| match_origin(pattern, &origin))
+    }
+
+    fn is_allowed_header(&self, header: &str) -> bool {
+        self.any_header || self.headers.iter().any(|h| h.eq_ignore_ascii_case(header))
+    }
+
+    /// Add the headers common to preflight and actual responses.
+    fn set_origin_headers(&self, response: &mut Response, origin: &str) {
+        // NOTE: Credentialed requests may not use the "*" wildcard
+        if self.any_origin && !self.credentials {
+            response.headers.set_raw("Access-Control-Allow-Origin", vec![b"*".to_vec()]);
+        }
+        else {
+            response.headers.set_raw("Access-Control-Allow-Origin", vec![origin.as_bytes().to_vec()]);
+        }
+        if self.credentials {
+            response.headers.set_raw("Access-Control-Allow-Credentials", vec![b"true".to_vec()]);
+        }
+    }
+
+    fn preflight(&self, req: &Request, origin: &str, method: &str) -> Response {
+        let method_allowed = self.methods.iter().any(|m| m.as_ref() == method);
+        let mut requested_headers = Vec::new();
+        for value in req.headers.get_raw("Access-Control-Request-Headers").unwrap_or(&[]) {
+            let value = String::from_utf8_lossy(value);
+            requested_headers.extend(value.split(',').map(|h| h.trim().to_string()).filter(|h| !h.is_empty()));
+        }
+        let headers_allowed = requested_headers.iter().all(|h| self.is_allowed_header(h));
+
+        if !method_allowed || !headers_allowed {
+            return Response::with(status::Forbidden);
+        }
+
+        let mut response = Response::with(status::NoContent);
+        self.set_origin_headers(&mut response, origin);
+
+        let methods: Vec<&str> = self.methods.iter().map(|m| m.as_ref()).collect();
+        response.headers.set_raw("Access-Control-Allow-Methods", vec![methods.join(", ").into_bytes()]);
+        if !requested_headers.is_empty() {
+            // NOTE: Echo the requested headers, which have already been checked
+            response.headers.set_raw("Access-Control-Allow-Headers", vec![requested_headers.join(", ").into_bytes()]);
+        }
+        if let Some(max_age) = self.max_age {
+            response.headers.set_raw("Access-Control-Max-Age", vec![max_age.to_string().into_bytes()]);
+        }
+        response
+    }
+
+    /// Test whether responses depend on the `Origin` request header, and so must
+    /// be marked with `Vary: Origin` to be cached correctly.
+    fn varies_by_origin(&self) -> bool {
+        !self.any_origin || self.credentials
+    }
+
+    fn respond(&self, req: &mut Request, next: PipelineNext) -> IronResult<Response> {
+        let origin = match header_value(req, "Origin") {
+            Some(origin) => origin,
+            None         => return next.process(req)
+        };
+
+        let preflight_method = if req.method == Method::Options {
+            header_value(req, "Access-Control-Request-Method")
+        } else {
+            None
+        };
+
+        if !self.is_allowed_origin(&origin) {
+            if preflight_method.is_some() {
+                return Ok(Response::with(status::Forbidden));
+            }
+            return next.process(req);
+        }
+
+        if let Some(method) = preflight_method {
+            return Ok(self.preflight(req, &origin, &method));
+        }
+
+        let mut result = next.process(req);
+        {
+            let response = match result {
+                Ok(ref mut response) => response,
+                Err(ref mut err)     => &mut err.response
+            };
+            self.set_origin_headers(response, &origin);
+            if !self.expose_headers.is_empty() {
+                response.headers.set_raw("Access-Control-Expose-Headers", vec![self.expose_headers.join(", ").into_bytes()]);
+            }
+        }
+        result
+    }
+}
+
+impl Default for Cors {
+    fn default() -> Cors {
+        Cors::new()
+    }
+}
+
+fn header_value(req: &Request, name: &str) -> Option<String> {
+    req.headers.get_raw(name)
+        .and_then(|values| values.first())
+        .map(|value| String::from_utf8_lossy(value).trim().to_string())
+}
+
+impl Middleware for Cors {
+    fn process(&self, req: &mut Request, next: PipelineNext) -> IronResult<Response> {
+        let mut result = self.respond(req, next);
+        if self.varies_by_origin() {
+            // NOTE: Including responses to requests without an allowed origin, which
+            // must not be served from a cache to requests from other origins
+            let response = match result {
+                Ok(ref mut response) => response,
+                Err(ref mut err)     => &mut err.response
+            };
+            response.headers.append_raw("Vary", b"Origin".to_vec());
+        }
+        result
+    }
+
+    fn describe(&self) -> MiddlewareDescription {
+        let origins = if self.any_origin { "*".to_string() } else { self.origins.join(", ") };
+        MiddlewareDescription::new("Cors").detail(format!("origins({})", origins))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::match_origin;
+
+    #[test]
+    fn match_origin_exact() {
+        assert!(match_origin("https://example.com", "https://example.com"));
+        assert!(!match_origin("https://example.com", "https://example.com.evil.com"));
+        assert!(!match_origin("https://example.com", "http://example.com"));
+    }
+
+    #[test]
+    fn match_origin_wildcard() {
+        assert!(match_origin("https://*.example.com", "https://app.example.com"));
+        assert!(match_origin("https://*.example.com", "https://a.b.example.com"));
+        assert!(!match_origin("https://*.example.com", "https://example.com"));
+        assert!(!match_origin("https://*.example.com", "https://evil.com/.example.com"));
+        assert!(!match_origin("https://*.example.com", "https://app.example.com.evil.com"));
+        assert!(match_origin("http://localhost:*", "http://localhost:3000"));
+        assert!(!match_origin("http://localhost:*", "http://localhost:3000/x"));
+    }
+}
@@ -0,0 +1,243 @@
+use iron;
+use iron::prelude::*;
+use iron::status;
+use iron::headers::ContentLength;
+
+use std::error;
+use std::fmt;
+use std::io::{self, Read};
+
+use {Middleware, PipelineNext};
+use describe::MiddlewareDescription;
+use middleware::compress::Encoding;
+
+/// Middleware which decodes request bodies sent with a `Content-Encoding`
+/// of `gzip` or `deflate` (or `br`, when the `brotli` cargo feature is enabled).
+///
+/// # Important
+/// Iron does not allow the request body to be replaced, so the decoded body is
+/// read into memory and stored in the `DecodedBody` request extension. **Once a
+/// body has been decoded, `req.body` is empty**: handlers and body parsers must
+/// use `body_reader` (or the `DecodedBody` extension) to read it. The
+/// `Content-Encoding` request header is removed and `Content-Length` is set to
+/// the length of the decoded body.
+///
+/// Requests whose decoded body exceeds the maximum size (10 MiB by default) fail
+/// with `413 Payload Too Large`, which protects against "decompression bombs".
+/// Requests with an unsupported encoding fail with `415 Unsupported Media Type`,
+/// and requests whose body cannot be decoded fail with `400 Bad Request`.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate iron;
+/// # extern crate iron_pipeline;
+/// # use iron::prelude::*;
+/// # use iron_pipeline::prelude::*;
+/// use std::io::Read;
+/// use iron_pipeline::middleware::decompress::{Decompress, body_reader};
+///
+/// # fn main() {
+/// # let mut pipeline = Pipeline::new();
+/// pipeline.add(Decompress::new().max_size(64 * 1024 * 1024));
+/// pipeline.add(Handle(|req| {
+///     let mut body = String::new();
+///     body_reader(req).read_to_string(&mut body).unwrap();
+///     Ok(Response::with(format!("Received {} bytes", body.len())))
+/// }));
+/// # }
+/// ```
+pub struct Decompress {
+    max_size: usize
+}
+
+/// Request extension containing the request body decoded by `Decompress`.
+pub struct DecodedBody;
+impl iron::typemap::Key for DecodedBody {
+    type Value = Vec<u8>;
+}
+
+/// Get a reader for the request body, which reads the body decoded by
+/// `Decompress` if any, and the raw request body otherwise.
+pub fn body_reader<'a>(req: &'a mut Request) -> Box<dyn Read + 'a> {
+    match req.extensions.get::<DecodedBody>() {
+        Some(body) => Box::new(&body[..]),
+        None       => Box::new(&mut req.body)
+    }
+}
+
+/// The kinds of error which may occur when decoding a request body.
+#[derive(Debug)]
+pub enum DecompressError {
+    /// The request body uses an unsupported content encoding
+    UnsupportedEncoding(String),
+    /// The decoded request body is larger than the maximum size
+    TooLarge(usize),
+    /// The request body could not be decoded
+    Invalid(io::Error)
+}
+
+impl fmt::Display for DecompressError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        #[allow(deprecated)]
+        let description = error::Error::description(self);
+        match *self {
+            DecompressError::UnsupportedEncoding(ref encoding) => write!(fmt, "{} \"{}\"", description, encoding),
+            DecompressError::TooLarge(max_size)                => write!(fmt, "{} (maximum {} bytes)", description, max_size),
+            DecompressError::Invalid(ref err)                  => write!(fmt, "{}: {}", description, err)
+        }
+    }
+}
+
+impl error::Error for DecompressError {
+    fn description(&self) -> &'static str {
+        match *self {
+            DecompressError::UnsupportedEncoding(_) => "Unsupported request content encoding",
+            DecompressError::TooLarge(_)            => "Decoded request body is too large",
+            DecompressError::Invalid(_)             => "Request body could not be decoded"
+        }
+    }
+
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            DecompressError::Invalid(ref err) => Some(err),
+            _                                 => None
+        }
+    }
+}
+
+impl DecompressError {
+    fn status(&self) -> status::Status {
+        match *self {
+            DecompressError::UnsupportedEncoding(_) => status::UnsupportedMediaType,
+            DecompressError::TooLarge(_)            => status::PayloadTooLarge,
+            DecompressError::Invalid(_)             => status::BadRequest
+        }
+    }
+}
+
+/// Decode the body, failing if the decoded body is larger than `max_size` bytes.
+fn decode<R>(encoding: Encoding, body: R, max_size: usize) -> Result<Vec<u8>, DecompressError>
+    where R: Read
+{
+    let decoder = encoding.decoder(body).map_err(DecompressError::Invalid)?;
+    let mut decoded = Vec::new();
+    decoder.take(max_size as u64 + 1).read_to_end(&mut decoded).map_err(DecompressError::Invalid)?;
+    if decoded.len() > max_size {
+        return Err(DecompressError::TooLarge(max_size));
+    }
+    Ok(decoded)
+}
+
+impl Decompress {
+    /// Construct a new decompression middleware with a maximum decoded body size of 10 MiB.
+    pub fn new() -> Decompress {
+        Decompress { max_size: 10 * 1024 * 1024 }
+    }
+
+    /// Set the maximum size of a decoded request body, in bytes.
+    pub fn max_size(mut self, bytes: usize) -> Decompress {
+        self.max_size = bytes;
+        self
+    }
+
+    fn decode_request(&self, req: &mut Request) -> Result<(), DecompressError> {
+        let content_encoding = match req.headers.get_raw("Content-Encoding").and_then(|values| values.first()) {
+            Some(value) => String::from_utf8_lossy(value).trim().to_string(),
+            None        => return Ok(())
+        };
+        if content_encoding.is_empty() || content_encoding.eq_ignore_ascii_case("identity") {
+            return Ok(());
+        }
+
+        // NOTE: Multiple encodings (e.g. "gzip, br") are not supported
+        let encoding = Encoding::from_name(&content_encoding)
+            .ok_or_else(|| DecompressError::UnsupportedEncoding(content_encoding.clone()))?;
+
+        let decoded = decode(encoding, &mut req.body, self.max_size)?;
+        req.headers.remove_raw("Content-Encoding");
+        req.headers.set(ContentLength(decoded.len() as u64));
+        req.extensions.insert::<DecodedBody>(decoded);
+        Ok(())
+    }
+}
+
+impl Default for Decompress {
+    fn default() -> Decompress {
+        Decompress::new()
+    }
+}
+
+impl Middleware for Decompress {
+    fn process(&self, req: &mut Request, next: PipelineNext) -> IronResult<Response> {
+        if let Err(err) = self.decode_request(req) {
+            let mut response = Response::with(err.status());
+            if let DecompressError::UnsupportedEncoding(_) = err {
+                let encodings: Vec<&str> = Encoding::all().iter().map(|e| e.name()).collect();
+                response.headers.set_raw("Accept-Encoding", vec![encodings.join(", ").into_bytes()]);
+            }
+            return Err(IronError { error: Box::new(err), response });
+        }
+        next.process(req)
+    }
+
+    fn describe(&self) -> MiddlewareDescription {
+        MiddlewareDescription::new("Decompress").detail(format!("max {} bytes", self.max_size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::io::Write;
+
+    use flate2;
+    use flate2::write::{GzEncoder, ZlibEncoder, DeflateEncoder};
+
+    use super::{decode, DecompressError};
+    use middleware::compress::Encoding;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn decode_gzip() {
+        let decoded = decode(Encoding::Gzip, &gzip(b"hello world")[..], 1024).unwrap();
+        assert_eq!(decoded, b"hello world");
+    }
+
+    #[test]
+    fn decode_deflate_zlib_or_raw() {
+        let mut zlib = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+        zlib.write_all(b"hello zlib").unwrap();
+        let decoded = decode(Encoding::Deflate, &zlib.finish().unwrap()[..], 1024).unwrap();
+        assert_eq!(decoded, b"hello zlib");
+
+        let mut raw = DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        raw.write_all(b"hello deflate").unwrap();
+        let decoded = decode(Encoding::Deflate, &raw.finish().unwrap()[..], 1024).unwrap();
+        assert_eq!(decoded, b"hello deflate");
+    }
+
+    #[test]
+    fn decode_enforces_max_size() {
+        let bomb = gzip(&vec![0; 1024 * 1024]);
+        assert!(bomb.len() < 4096);
+        match decode(Encoding::Gzip, &bomb[..], 64 * 1024) {
+            Err(DecompressError::TooLarge(max_size)) => assert_eq!(max_size, 64 * 1024),
+            other                                    => panic!("unexpected result {:?}", other)
+        }
+        assert_eq!(decode(Encoding::Gzip, &bomb[..], 1024 * 1024).unwrap().len(), 1024 * 1024);
+    }
+
+    #[test]
+    fn decode_invalid() {
+        match decode(Encoding::Gzip, &b"not gzip"[..], 1024) {
+            Err(DecompressError::Invalid(_)) => {},
+            other                            => panic!("unexpected result {:?}", other)
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use iron::prelude::*;
+use iron::status;
+use iron::error::Error as IronErrorTrait;
+
+use std::any;
+
+use {Middleware, PipelineNext, Error};
+use describe::{MiddlewareDescription, short_type_name};
+
+/// Function which maps an error to a replacement response, or `None`
+/// if the error is not handled.
+type ErrorHandlerFn = dyn Fn(&IronError, &Request) -> Option<Response> + Send + Sync;
+
+/// Middleware which turns errors raised by the middleware after it into responses.
+///
+/// Handlers may be registered for a concrete error type (downcast from
+/// `IronError::error`) or for the status code of the error response, and are
+/// tried in the order in which they were registered. The first handler which
+/// matches replaces the error with a successful response. Errors which match no
+/// handler are returned unchanged.
+///
+/// # Examples
+/// Render all missing handler and 404 errors as the same JSON document:
+///
+/// ```rust
+/// # extern crate iron;
+/// # extern crate iron_pipeline;
+/// # use iron::prelude::*;
+/// # use iron::status;
+/// # use iron_pipeline::prelude::*;
+/// # fn main() {
+/// # let mut pipeline = Pipeline::new();
+/// pipeline.add(ErrorBoundary::new()
+///     .on_no_handler(|_, _| Response::with((status::NotFound, r#"{"error":"not_found"}"#)))
+///     .on_status(status::NotFound, |_, _| Response::with((status::NotFound, r#"{"error":"not_found"}"#))));
+/// # }
+/// ```
+pub struct ErrorBoundary {
+    handlers: Vec<(String, Box<ErrorHandlerFn>)>
+}
+
+impl ErrorBoundary {
+    /// Construct a new error boundary with no handlers.
+    pub fn new() -> ErrorBoundary {
+        ErrorBoundary { handlers: Vec::new() }
+    }
+
+    /// Handle errors of type `E`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # extern crate iron;
+    /// # extern crate iron_pipeline;
+    /// # use iron::prelude::*;
+    /// # use iron::status;
+    /// # use iron_pipeline::prelude::*;
+    /// use iron_pipeline::middleware::version::VersionError;
+    ///
+    /// # fn main() {
+    /// # let mut pipeline = Pipeline::new();
+    /// pipeline.add(ErrorBoundary::new().on(|err: &VersionError, _| {
+    ///     Response::with((status::BadRequest, format!("Try one of: {}", err.supported.join(", "))))
+    /// }));
+    /// # }
+    /// ```
+    pub fn on<E, F>(mut self, handler: F) -> ErrorBoundary
+        where E: IronErrorTrait + 'static,
+              F: Fn(&E, &Request) -> Response + Send + Sync + 'static
+    {
+        let name = short_type_name(any::type_name::<E>());
+        self.handlers.push((name, Box::new(move |err: &IronError, req: &Request| {
+            err.error.downcast_ref::<E>().map(|e| handler(e, req))
+        })));
+        self
+    }
+
+    /// Handle errors whose response has the given status code.
+    pub fn on_status<F>(mut self, status: status::Status, handler: F) -> ErrorBoundary
+        where F: Fn(&IronError, &Request) -> Response + Send + Sync + 'static
+    {
+        self.handlers.push((status.to_string(), Box::new(move |err: &IronError, req: &Request| {
+            if err.response.status == Some(status) { Some(handler(err, req)) } else { None }
+        })));
+        self
+    }
+
+    /// Handle `Error::NoHandler`, raised when a request is passed along by every
+    /// middleware in a pipeline whose fallback is `Fallback::Raise`.
+    pub fn on_no_handler<F>(mut self, handler: F) -> ErrorBoundary
+        where F: Fn(&Error, &Request) -> Response + Send + Sync + 'static
+    {
+        self.handlers.push(("NoHandler".to_string(), Box::new(move |err: &IronError, req: &Request| {
+            match err.error.downcast_ref::<Error>() {
+                Some(e @ &Error::NoHandler { .. }) => Some(handler(e, req)),
+                _                                  => None
+            }
+        })));
+        self
+    }
+
+    /// Handle all errors not handled by an earlier handler.
+    pub fn otherwise<F>(mut self, handler: F) -> ErrorBoundary
+        where F: Fn(&IronError, &Request) -> Response + Send + Sync + 'static
+    {
+        self.handlers.push(("*".to_string(), Box::new(move |err: &IronError, req: &Request| {
+            Some(handler(err, req))
+        })));
+        self
+    }
+}
+
+impl Default for ErrorBoundary {
+    fn default() -> ErrorBoundary {
+        ErrorBoundary::new()
+    }
+}
+
+impl Middleware for ErrorBoundary {
+    fn process(&self, req: &mut Request, next: PipelineNext) -> IronResult<Response> {
+        let err = match next.process(req) {
+            Ok(response) => return Ok(response),
+            Err(err)     => err
+        };
+        for (_, handler) in &self.handlers {
+            if let Some(response) = handler(&err, req) {
+                return Ok(response);
+            }
+        }
+        Err(err)
+    }
+
+    fn describe(&self) -> MiddlewareDescription {
+        let names: Vec<&str> = self.handlers.iter().map(|h| &h.0[..]).collect();
+        MiddlewareDescription::new("ErrorBoundary").detail(format!("on({})", names.join(", ")))
+    }
+}
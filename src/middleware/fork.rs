@@ -3,121 +3,576 @@ use iron::prelude::*;
 use iron::middleware::Handler;
 
 use url::Url;
+use url::percent_encoding::percent_decode;
 
+use std::any;
+use std::cell::Cell;
 use std::error;
 use std::fmt;
 
 use {Pipeline, Middleware, PipelineNext};
+use describe::{MiddlewareDescription, short_type_name};
+use middleware::split::{Split, Sticky};
+use predicate::{Predicate, request_host, forwarded_host, match_host, split_host_pattern};
 
-// Track errors parsing fork paths
-#[derive(Debug, PartialEq)]
-enum ParsePathError {
+/// The kinds of error which may occur when parsing a fork path.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathErrorKind {
+    /// The path does not start with `/`
     NoLeadingSlash,
-    PathEmpty
+    /// The path contains no segments
+    PathEmpty,
+    /// A `:parameter` or `*wildcard` segment has no name
+    MissingName,
+    /// The same parameter name is used more than once
+    DuplicateName,
+    /// A `*wildcard` segment is not the last segment in the path
+    WildcardNotLast
 }
 
-impl fmt::Display for ParsePathError {
+/// Error raised when a fork path cannot be parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PathError {
+    path: String,
+    kind: PathErrorKind
+}
+
+impl PathError {
+    fn new(path: &str, kind: PathErrorKind) -> PathError {
+        PathError { path: path.to_string(), kind }
+    }
+
+    /// The path which could not be parsed.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The kind of error.
+    pub fn kind(&self) -> PathErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for PathError {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        write!(fmt, "{}", error::Error::description(self))
+        #[allow(deprecated)]
+        let description = error::Error::description(self);
+        write!(fmt, "Invalid fork path \"{}\": {}", self.path, description)
     }
 }
 
-impl error::Error for ParsePathError {
+impl error::Error for PathError {
     fn description(&self) -> &'static str {
+        match self.kind {
+            PathErrorKind::NoLeadingSlash  => "Path must start with /",
+            PathErrorKind::PathEmpty       => "Path cannot be empty",
+            PathErrorKind::MissingName     => "Path parameters and wildcards must be named",
+            PathErrorKind::DuplicateName   => "Path parameter names must be unique",
+            PathErrorKind::WildcardNotLast => "Path wildcards must be the last segment"
+        }
+    }
+}
+
+/// A segment of a fork path.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Segment {
+    /// Matches the segment exactly, e.g. `api`
+    Literal(String),
+    /// Matches and captures any one non-empty segment, e.g. `:tenant_id`
+    Param(String),
+    /// Matches and captures all remaining segments (if any), e.g. `*rest`
+    Wildcard(String)
+}
+
+impl Segment {
+    /// Whether the segment matches the given URL path segment.
+    fn matches(&self, segment: &str) -> bool {
         match *self {
-            ParsePathError::NoLeadingSlash => "Path must start with /",
-            ParsePathError::PathEmpty      => "Path cannot be empty"
+            Segment::Literal(ref literal) => literal == segment,
+            Segment::Param(_)             => !segment.is_empty(),
+            Segment::Wildcard(_)          => true
         }
     }
 }
 
-fn parse_path(path: &str) -> Result<Vec<String>, ParsePathError> {
-    if !path.starts_with("/") {
-        return Err(ParsePathError::NoLeadingSlash);
+impl fmt::Display for Segment {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Segment::Literal(ref literal) => write!(fmt, "{}", literal),
+            Segment::Param(ref name)      => write!(fmt, ":{}", name),
+            Segment::Wildcard(ref name)   => write!(fmt, "*{}", name)
+        }
+    }
+}
+
+pub(crate) fn parse_path(path: &str) -> Result<Vec<Segment>, PathError> {
+    if !path.starts_with('/') {
+        return Err(PathError::new(path, PathErrorKind::NoLeadingSlash));
     }
 
-    let segments: Vec<_> =
-        path.trim_left_matches("/").split("/")
-            .filter(|s| s.len() > 0)
-            .map(|s| s.to_string())
-            .collect();
+    let mut segments = Vec::new();
+    let mut names = Vec::new();
+
+    for s in path.trim_start_matches('/').split('/').filter(|s| !s.is_empty()) {
+        if let Some(&Segment::Wildcard(_)) = segments.last() {
+            return Err(PathError::new(path, PathErrorKind::WildcardNotLast));
+        }
+
+        let segment = match s.chars().next() {
+            Some(':') => Segment::Param(s[1..].to_string()),
+            Some('*') => Segment::Wildcard(s[1..].to_string()),
+            _         => Segment::Literal(s.to_string())
+        };
+
+        match segment {
+            Segment::Param(ref name) | Segment::Wildcard(ref name) => {
+                if name.is_empty() {
+                    return Err(PathError::new(path, PathErrorKind::MissingName));
+                }
+                if names.contains(name) {
+                    return Err(PathError::new(path, PathErrorKind::DuplicateName));
+                }
+                names.push(name.clone());
+            },
+            Segment::Literal(_) => {}
+        }
+
+        segments.push(segment);
+    }
 
-    if segments.len() == 0 {
-        return Err(ParsePathError::PathEmpty);
+    if segments.is_empty() {
+        return Err(PathError::new(path, PathErrorKind::PathEmpty));
     }
 
     Ok(segments)
 }
 
+/// Match a fork path (which may end with a wildcard) against the request path,
+/// returning the number of fixed segments to strip from the request URL.
+/// The segments matched by a wildcard are not stripped.
+fn match_path(pattern: &[Segment], path: &[&str]) -> Option<usize> {
+    let fixed = match pattern.last() {
+        Some(&Segment::Wildcard(_)) => &pattern[..pattern.len() - 1],
+        _                           => pattern
+    };
+    if slice_starts_with(path, fixed, |s, segment| segment.matches(s)) { Some(fixed.len()) } else { None }
+}
+
+/// Collect the values captured by the parameters and wildcard in a matched fork path.
+fn capture_params(pattern: &[Segment], path: &[&str], params: &mut Params) {
+    for (i, segment) in pattern.iter().enumerate() {
+        match *segment {
+            Segment::Literal(_) => {},
+            Segment::Param(ref name) => {
+                params.insert(name.clone(), decode(path[i]));
+            },
+            Segment::Wildcard(ref name) => {
+                params.insert(name.clone(), decode(&path[i..].join("/")));
+            }
+        }
+    }
+}
+
+pub(crate) fn decode(value: &str) -> String {
+    percent_decode(value.as_bytes()).decode_utf8_lossy().into_owned()
+}
+
 /// Middleware which optionally delegates to a sub pipeline
 /// based on some predicate applied to each request.
-pub struct Fork<P>(Pipeline, P);
+pub struct Fork<P> {
+    pipeline: Pipeline,
+    handler: P,
+    fallthrough: bool
+}
 
-/// Internal trait used to determine if a request should
-/// branch to the sub-pipeline.
-#[doc(hidden)]
+/// Trait which determines if a request should branch to the sub pipeline of a
+/// `Fork`, and how the request is modified when it does.
+///
+/// Implement this trait to define a custom fork strategy, and construct the
+/// fork with `Fork::new`.
+///
+/// # Examples
+/// A fork which handles requests with a `?preview` query string, and records
+/// whether the preview pipeline produced a response:
+///
+/// ```rust
+/// # extern crate iron;
+/// # extern crate iron_pipeline;
+/// # use iron::prelude::*;
+/// # use iron_pipeline::prelude::*;
+/// use iron_pipeline::middleware::fork::ForkHandler;
+///
+/// struct Preview;
+///
+/// struct Previewed;
+/// impl iron::typemap::Key for Previewed {
+///     type Value = bool;
+/// }
+///
+/// impl ForkHandler for Preview {
+///     fn should_fork(&self, req: &Request) -> bool {
+///         req.url.query() == Some("preview")
+///     }
+///
+///     fn after_fork(&self, req: &mut Request, result: &mut IronResult<Response>) {
+///         req.extensions.insert::<Previewed>(result.is_ok());
+///     }
+///
+///     fn describe(&self) -> String {
+///         "preview".to_string()
+///     }
+/// }
+///
+/// # fn main() {
+/// # let mut pipeline = Pipeline::new();
+/// pipeline.add(Fork::new(Preview, |preview| {
+///     preview.add(Handle(|_| Ok(Response::with("Hello from iron-pipeline"))));
+/// }));
+/// # }
+/// ```
 pub trait ForkHandler {
+    /// Determine whether the request should branch to the sub pipeline.
     fn should_fork(&self, req: &Request) -> bool;
+
+    /// Modify the request before it is passed to the sub pipeline,
+    /// e.g. to strip a path prefix. Does nothing by default.
+    ///
+    /// Only invoked when `should_fork` returns **true**.
     fn modify_request(&self, _: &mut Request) {
         // nop
     }
+
+    /// Inspect or replace the result of the sub pipeline, and restore or
+    /// annotate the request, before the result is returned. Does nothing by default.
+    ///
+    /// Not invoked for requests which fall through to the parent pipeline
+    /// (see `Fork::fallthrough`), as any changes made by the fork have already been undone.
+    fn after_fork(&self, _: &mut Request, _: &mut IronResult<Response>) {
+        // nop
+    }
+
+    /// Describe the fork for `Pipeline::describe`, e.g. `when_path(/api)`.
+    /// Defaults to the name of the handler type.
+    fn describe(&self) -> String {
+        short_type_name(any::type_name::<Self>())
+    }
+
+    /// The path prefix stripped by `modify_request`, if any.
+    /// Used by `Pipeline::build` to detect shadowed paths.
+    fn path(&self) -> Option<String> {
+        None
+    }
 }
 
 /// Branch when the request matches the predicate P.
-#[doc(hidden)]
+/// Created by `Fork::when` and `Fork::when_matches`.
 pub struct ForkOnFn<P>(P);
 
 impl<P> ForkHandler for ForkOnFn<P>
-    where P: Fn(&Request) -> bool + Send + Sync
+    where P: Predicate
 {
     fn should_fork(&self, req: &Request) -> bool {
         let ForkOnFn(ref pred) = *self;
-        pred(req)
+        pred.matches(req)
+    }
+
+    fn describe(&self) -> String {
+        let ForkOnFn(ref pred) = *self;
+        format!("when({})", pred.describe())
     }
 }
 
 /// Branch when the request URL starts with the given segments.
-#[doc(hidden)]
-pub struct ForkOnPath(Vec<String>);
+/// Created by `Fork::when_path`.
+pub struct ForkOnPath(Vec<Segment>);
+
+/// Branch when the request host matches the given pattern.
+/// Created by `Fork::when_host`.
+pub struct ForkOnHost {
+    pattern: String,
+    trust_forwarded_host: bool
+}
 
 pub struct OriginalUrl;
 impl iron::typemap::Key for OriginalUrl {
     type Value = iron::Url;
 }
 
+/// Request extension which records the path prefix stripped by each
+/// `Fork::when_path` taken by the request, outermost first.
+///
+/// # Examples
+/// Build a URL to a resource relative to the path at which an application is mounted:
+///
+/// ```rust
+/// # extern crate iron;
+/// # extern crate iron_pipeline;
+/// # use iron::prelude::*;
+/// # use iron::status;
+/// # use iron::modifiers::RedirectRaw;
+/// # use iron_pipeline::prelude::*;
+/// # use iron_pipeline::middleware::fork::absolute_path;
+/// # fn main() {
+/// # let mut pipeline = Pipeline::new();
+/// pipeline.add(Fork::when_path("/app", |app| {
+///     app.add(Handle(|req| {
+///         // Redirects to "/app/login"
+///         Ok(Response::with((status::Found, RedirectRaw(absolute_path(req, "/login")))))
+///     }));
+/// }))
+/// # }
+/// ```
+pub struct MountPath;
+impl iron::typemap::Key for MountPath {
+    type Value = Mounts;
+}
+
+/// The path prefixes stripped from a request, outermost first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Mounts(Vec<String>);
+
+impl Mounts {
+    /// Each stripped path prefix, outermost first, e.g. `["/tenants/acme", "/api"]`.
+    pub fn prefixes(&self) -> &[String] {
+        &self.0
+    }
+
+    /// The cumulative path prefix, e.g. `/tenants/acme/api`.
+    pub fn path_base(&self) -> String {
+        self.0.concat()
+    }
+
+    pub(crate) fn push(&mut self, prefix: String) {
+        self.0.push(prefix);
+    }
+}
+
+/// Get the cumulative path prefix the request is currently mounted under,
+/// or an empty string if no path prefix has been stripped.
+pub fn mount_path(req: &Request) -> String {
+    req.extensions.get::<MountPath>().map(|m| m.path_base()).unwrap_or_default()
+}
+
+/// Convert a path relative to the path the request is currently mounted
+/// under into an absolute path.
+pub fn absolute_path(req: &Request, path: &str) -> String {
+    let base = mount_path(req);
+    match (base.is_empty(), path.starts_with('/')) {
+        (true, true)   => path.to_string(),
+        (true, false)  => format!("/{}", path),
+        (false, true)  => format!("{}{}", base, path),
+        (false, false) => format!("{}/{}", base, path)
+    }
+}
+
+/// Convert a path relative to the path the request is currently mounted
+/// under into an absolute URL, using the scheme, host and port of the original
+/// request URL.
+pub fn absolute_url(req: &Request, path: &str) -> iron::Url {
+    let mut url: Url = req.extensions.get::<OriginalUrl>().unwrap_or(&req.url).clone().into();
+    url.set_path(&absolute_path(req, path));
+    url.set_query(None);
+    url.set_fragment(None);
+    iron::Url::from_generic_url(url).unwrap()
+}
+
+/// Request extension containing the values captured by the `:parameter`
+/// and `*wildcard` segments of any fork paths matched by the request.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate iron;
+/// # extern crate iron_pipeline;
+/// # use iron::prelude::*;
+/// # use iron_pipeline::prelude::*;
+/// # use iron_pipeline::middleware::fork::RouteParams;
+/// # fn main() {
+/// # let mut pipeline = Pipeline::new();
+/// pipeline.add(Fork::when_path("/tenants/:tenant_id/api", |api| {
+///     api.add(Handle(|req| {
+///         let tenant_id = req.extensions.get::<RouteParams>().unwrap().get("tenant_id").unwrap();
+///         Ok(Response::with(format!("Hello, {}", tenant_id)))
+///     }));
+/// }))
+/// # }
+/// ```
+pub struct RouteParams;
+impl iron::typemap::Key for RouteParams {
+    type Value = Params;
+}
+
+/// Named values captured from the request path.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Params(Vec<(String, String)>);
+
+impl Params {
+    /// Get the value captured by the named parameter.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0.iter().find(|p| p.0 == name).map(|p| &p.1[..])
+    }
+
+    /// Set the value of the named parameter, replacing any existing value.
+    pub fn insert(&mut self, name: String, value: String) {
+        match self.0.iter_mut().find(|p| p.0 == name) {
+            Some(param) => param.1 = value,
+            None        => self.0.push((name, value))
+        }
+    }
+
+    /// Iterate over each parameter name and value.
+    pub fn iter(&self) -> ::std::slice::Iter<'_, (String, String)> {
+        self.0.iter()
+    }
+}
+
+/// Request extension containing the labels matched by the `*` label of
+/// the most recent `Fork::when_host` pattern taken by the request.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate iron;
+/// # extern crate iron_pipeline;
+/// # use iron::prelude::*;
+/// # use iron_pipeline::prelude::*;
+/// # use iron_pipeline::middleware::fork::Subdomain;
+/// # fn main() {
+/// # let mut pipeline = Pipeline::new();
+/// pipeline.add(Fork::when_host("*.tenant.example.com", |tenants| {
+///     tenants.add(Handle(|req| {
+///         // "acme" for requests to "acme.tenant.example.com"
+///         let tenant = req.extensions.get::<Subdomain>().unwrap();
+///         Ok(Response::with(format!("Hello, {}", tenant)))
+///     }));
+/// }))
+/// # }
+/// ```
+pub struct Subdomain;
+impl iron::typemap::Key for Subdomain {
+    type Value = String;
+}
+
+/// Request extension which records a description of each fork
+/// taken by the request, outermost first.
+pub struct ForkTrail;
+impl iron::typemap::Key for ForkTrail {
+    type Value = Vec<String>;
+}
+
 impl ForkHandler for ForkOnPath {
     fn should_fork(&self, req: &Request) -> bool {
         let ForkOnPath(ref path_segments) = *self;
-        slice_starts_with(&req.url.path(), path_segments)
+        match_path(path_segments, &req.url.path()).is_some()
     }
 
     fn modify_request(&self, req: &mut Request) {
         let ForkOnPath(ref path_segments) = *self;
 
-        // Strip the path prefix from the Url
-        let new_path = req.url.path()[path_segments.len()..].join("/");
-        let mut new_url: Url = req.url.clone().into();
-        new_url.set_path(&new_path);
+        let matched = {
+            let path = req.url.path();
+            let matched = match_path(path_segments, &path).expect("path must match");
+
+            // Capture any path parameters
+            capture_params(path_segments, &path, req.extensions.entry::<RouteParams>().or_insert_with(Params::default));
+            matched
+        };
+
+        strip_prefix(req, matched);
+    }
+
+    fn describe(&self) -> String {
+        format!("when_path({})", self.path().unwrap())
+    }
 
-        // Make the original (root) URL accessible
-        req.extensions.entry::<OriginalUrl>().or_insert(req.url.clone());
+    fn path(&self) -> Option<String> {
+        let ForkOnPath(ref path_segments) = *self;
+        Some(path_segments.iter().map(|s| format!("/{}", s)).collect())
+    }
+}
 
-        // Overwrite the request Url
-        req.url = iron::Url::from_generic_url(new_url).unwrap();
+impl ForkOnHost {
+    fn host(&self, req: &Request) -> (String, u16) {
+        if self.trust_forwarded_host {
+            if let Some(host) = forwarded_host(req) {
+                return host;
+            }
+        }
+        request_host(req)
     }
 }
 
-fn slice_starts_with<A, B>(input: &[A], prefix: &[B]) -> bool
-    where A: PartialEq<B>
+impl ForkHandler for ForkOnHost {
+    fn should_fork(&self, req: &Request) -> bool {
+        let (hostname, port) = self.host(req);
+        match_host(&self.pattern, &hostname, port).is_some()
+    }
+
+    fn modify_request(&self, req: &mut Request) {
+        let (hostname, port) = self.host(req);
+        match match_host(&self.pattern, &hostname, port).expect("host must match") {
+            Some(subdomain) => { req.extensions.insert::<Subdomain>(subdomain); },
+            None            => { req.extensions.remove::<Subdomain>(); }
+        }
+    }
+
+    fn describe(&self) -> String {
+        if self.trust_forwarded_host {
+            format!("when_host({}) trust_forwarded_host", self.pattern)
+        }
+        else {
+            format!("when_host({})", self.pattern)
+        }
+    }
+}
+
+/// Strip the first `count` segments from the request URL, recording them in
+/// the `MountPath` extension, and record the original URL if not already recorded.
+pub(crate) fn strip_prefix(req: &mut Request, count: usize) {
+    let (prefix, new_path) = {
+        let path = req.url.path();
+        (format!("/{}", path[..count].join("/")), path[count..].join("/"))
+    };
+
+    // Record the stripped prefix
+    req.extensions.entry::<MountPath>().or_insert_with(Mounts::default).push(prefix);
+
+    let mut new_url: Url = req.url.clone().into();
+    new_url.set_path(&new_path);
+
+    // Make the original (root) URL accessible
+    req.extensions.entry::<OriginalUrl>().or_insert(req.url.clone());
+
+    // Overwrite the request Url
+    req.url = iron::Url::from_generic_url(new_url).unwrap();
+}
+
+fn slice_starts_with<A, B, F>(input: &[A], prefix: &[B], eq: F) -> bool
+    where F: Fn(&A, &B) -> bool
 {
     if prefix.len() > input.len() {
         return false;
     }
 
-    input.iter().zip(prefix).all(|(a, b)| a == b)
+    input.iter().zip(prefix).all(|(a, b)| eq(a, b))
 }
 
 impl Fork<()> {
+    /// Construct a new pipeline fork using a custom `ForkHandler`.
+    /// The `handler` is consulted on every request and determines whether to delegate
+    /// to the sub pipeline. The `pipeline_builder` is used to construct the sub pipeline,
+    /// and is executed immediately.
+    ///
+    /// See `ForkHandler` for an example.
+    pub fn new<H, B>(handler: H, pipeline_builder: B) -> Fork<H>
+        where B: FnOnce(&mut Pipeline),
+              H: ForkHandler
+    {
+        let mut sub_pipeline = Pipeline::new();
+        pipeline_builder(&mut sub_pipeline);
+        Fork { pipeline: sub_pipeline, handler, fallthrough: false }
+    }
+
     /// Construct a new pipeline fork.
     /// The `predicate` is executed on every request and determines whether to delegate to the
     /// sub pipeline. The `pipeline_builder` is used to construct the sub pipeline, and is
@@ -147,7 +602,38 @@ impl Fork<()> {
     {
         let mut sub_pipeline = Pipeline::new();
         pipeline_builder(&mut sub_pipeline);
-        Fork(sub_pipeline, ForkOnFn(predicate))
+        Fork { pipeline: sub_pipeline, handler: ForkOnFn(predicate), fallthrough: false }
+    }
+
+    /// Construct a new pipeline fork, as `when`, using a `Predicate` from the
+    /// `predicate` module (or any combination of predicates).
+    ///
+    /// # Examples
+    /// A sub pipeline which handles all "Post" requests with a JSON body:
+    ///
+    /// ```rust
+    /// # extern crate iron;
+    /// # extern crate iron_pipeline;
+    /// # use iron::prelude::*;
+    /// # use iron::method::Post;
+    /// # use iron_pipeline::prelude::*;
+    /// # use iron_pipeline::predicate::{Predicate, Method, ContentType};
+    /// # fn main() {
+    /// # let mut pipeline = Pipeline::new();
+    /// pipeline.add(Fork::when_matches(Method(Post).and(ContentType("application/json")), |sub_pipeline| {
+    ///     sub_pipeline.add(Handle(|req| {
+    ///         Ok(Response::with("Hello from iron-pipeline"))
+    ///     }));
+    /// }))
+    /// # }
+    /// ```
+    pub fn when_matches<P, B>(predicate: P, pipeline_builder: B) -> Fork<ForkOnFn<P>>
+        where B: FnOnce(&mut Pipeline),
+              P: Predicate
+    {
+        let mut sub_pipeline = Pipeline::new();
+        pipeline_builder(&mut sub_pipeline);
+        Fork { pipeline: sub_pipeline, handler: ForkOnFn(predicate), fallthrough: false }
     }
 
     /// Construct a new pipeline fork.
@@ -155,6 +641,11 @@ impl Fork<()> {
     /// whether to delegate to the sub pipeline. The `pipeline_builder` is used to
     /// construct the sub pipeline, and is executed immediately.
     ///
+    /// The path may contain `:parameter` segments which match any one segment, and
+    /// may end with a `*wildcard` segment which matches all remaining segments.
+    /// The matched values are stored in the `RouteParams` request extension.
+    /// Only the segments before the wildcard are stripped from the request URL.
+    ///
     /// # Examples
     /// A sub pipeline which handles all requests to "/api/v2":
     ///
@@ -175,14 +666,209 @@ impl Fork<()> {
     ///
     /// #Panics
     /// Panics when passed an invalid path string. Path should be of the form `/hello/world`.
+    /// See `try_when_path` for a version which returns an error instead.
     pub fn when_path<P, B>(path: P, pipeline_builder: B) -> Fork<ForkOnPath>
         where B: FnOnce(&mut Pipeline),
               P: AsRef<str>
     {
-        let segments = parse_path(path.as_ref()).unwrap();
+        Fork::try_when_path(path, pipeline_builder).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Construct a new pipeline fork, as `when_path`.
+    /// Returns an error when passed an invalid path string, in which case
+    /// the `pipeline_builder` is not executed.
+    ///
+    /// # Examples
+    /// A sub pipeline mounted at a path read from configuration:
+    ///
+    /// ```rust
+    /// # extern crate iron;
+    /// # extern crate iron_pipeline;
+    /// # use iron::prelude::*;
+    /// # use iron_pipeline::prelude::*;
+    /// # use iron_pipeline::middleware::fork::PathErrorKind;
+    /// # fn main() {
+    /// # let mut pipeline = Pipeline::new();
+    /// let mount_point = "api/v2"; // Oops! Missing the leading slash
+    /// match Fork::try_when_path(mount_point, |_| {}) {
+    ///     Ok(fork) => pipeline.add(fork),
+    ///     Err(err) => assert_eq!(err.kind(), PathErrorKind::NoLeadingSlash)
+    /// }
+    /// # }
+    /// ```
+    pub fn try_when_path<P, B>(path: P, pipeline_builder: B) -> Result<Fork<ForkOnPath>, PathError>
+        where B: FnOnce(&mut Pipeline),
+              P: AsRef<str>
+    {
+        let segments = parse_path(path.as_ref())?;
         let mut sub_pipeline = Pipeline::new();
         pipeline_builder(&mut sub_pipeline);
-        Fork(sub_pipeline, ForkOnPath(segments))
+        Ok(Fork { pipeline: sub_pipeline, handler: ForkOnPath(segments), fallthrough: false })
+    }
+
+    /// Construct a new pipeline fork.
+    /// The `host` pattern is compared against the `Host` header on every request and
+    /// determines whether to delegate to the sub pipeline. The `pipeline_builder` is
+    /// used to construct the sub pipeline, and is executed immediately.
+    ///
+    /// The pattern may start with a `*` label, which matches one or more labels and
+    /// stores them in the `Subdomain` request extension, and may end with a port.
+    /// IPv6 addresses must be bracketed, e.g. `[::1]:3000`.
+    /// Hosts are compared case-insensitively.
+    ///
+    /// #Panics
+    /// Panics when the pattern ends with an invalid port, e.g. `localhost:http`.
+    ///
+    /// # Examples
+    /// A sub pipeline which handles all requests to "api.example.com", and another
+    /// which handles requests to any subdomain of "tenant.example.com":
+    ///
+    /// ```rust
+    /// # extern crate iron;
+    /// # extern crate iron_pipeline;
+    /// # use iron::prelude::*;
+    /// # use iron_pipeline::prelude::*;
+    /// # fn main() {
+    /// # let mut pipeline = Pipeline::new();
+    /// pipeline.add(Fork::when_host("api.example.com", |api| {
+    ///     api.add(Handle(|req| {
+    ///         Ok(Response::with("Hello from iron-pipeline"))
+    ///     }));
+    /// }));
+    /// pipeline.add(Fork::when_host("*.tenant.example.com", |tenants| {
+    ///     tenants.add(Handle(|req| {
+    ///         Ok(Response::with("Hello, tenant"))
+    ///     }));
+    /// }));
+    /// # }
+    /// ```
+    pub fn when_host<H, B>(host: H, pipeline_builder: B) -> Fork<ForkOnHost>
+        where B: FnOnce(&mut Pipeline),
+              H: Into<String>
+    {
+        let pattern = host.into();
+        if let Err(err) = split_host_pattern(&pattern) {
+            panic!("{}", err);
+        }
+        let mut sub_pipeline = Pipeline::new();
+        pipeline_builder(&mut sub_pipeline);
+        let handler = ForkOnHost { pattern, trust_forwarded_host: false };
+        Fork { pipeline: sub_pipeline, handler, fallthrough: false }
+    }
+
+    /// Construct a new traffic split, which sends a share of requests to each of
+    /// several sub pipelines. Requests are assigned to a variant by the `sticky`
+    /// attribute of the request. Add variants with `Split::variant`.
+    ///
+    /// # Examples
+    /// Send 5% of users to a canary pipeline, assigning users by their user id:
+    ///
+    /// ```rust
+    /// # extern crate iron;
+    /// # extern crate iron_pipeline;
+    /// # use iron::prelude::*;
+    /// # use iron_pipeline::prelude::*;
+    /// # use iron_pipeline::middleware::split::Sticky;
+    /// # fn main() {
+    /// # let mut pipeline = Pipeline::new();
+    /// pipeline.add(Fork::split(Sticky::Header("X-User-Id".to_string()))
+    ///     .variant("stable", 95, |stable| {
+    ///         stable.add(Handle(|_| Ok(Response::with("Hello from iron-pipeline"))));
+    ///     })
+    ///     .variant("canary", 5, |canary| {
+    ///         canary.add(Handle(|_| Ok(Response::with("Hello from the canary"))));
+    ///     }));
+    /// # }
+    /// ```
+    pub fn split(sticky: Sticky) -> Split {
+        Split::new(sticky)
+    }
+}
+
+impl<P> Fork<P> {
+    /// Hand requests which are not handled by the sub pipeline back to the
+    /// next middleware in the parent pipeline, instead of invoking the sub
+    /// pipeline's fallback. Any changes made to the request URL by the fork
+    /// are undone first.
+    ///
+    /// # Examples
+    /// A sub pipeline which only handles "Get" requests to "/api", passing all
+    /// other requests to the rest of the parent pipeline:
+    ///
+    /// ```rust
+    /// # extern crate iron;
+    /// # extern crate iron_pipeline;
+    /// # use iron::prelude::*;
+    /// # use iron::method::Method;
+    /// # use iron_pipeline::prelude::*;
+    /// # fn main() {
+    /// # let mut pipeline = Pipeline::new();
+    /// pipeline.add(Fork::when_path("/api", |api| {
+    ///     api.add(Fork::when(|req| req.method == Method::Get, |get| {
+    ///         get.add(Handle(|req| {
+    ///             Ok(Response::with("Hello from iron-pipeline"))
+    ///         }));
+    ///     }));
+    /// }).fallthrough());
+    /// # }
+    /// ```
+    pub fn fallthrough(mut self) -> Fork<P> {
+        self.fallthrough = true;
+        self
+    }
+}
+
+impl Fork<ForkOnHost> {
+    /// Match against the first host in the `X-Forwarded-Host` header, when present,
+    /// instead of the `Host` header.
+    ///
+    /// Only use this when the server is behind a reverse proxy which sets (or strips)
+    /// the `X-Forwarded-Host` header, as otherwise clients may choose any host.
+    pub fn trust_forwarded_host(mut self) -> Fork<ForkOnHost> {
+        self.handler.trust_forwarded_host = true;
+        self
+    }
+}
+
+/// The parts of a request which are modified by a fork.
+struct Snapshot {
+    url: iron::Url,
+    original_url: Option<iron::Url>,
+    trail: Option<Vec<String>>,
+    params: Option<Params>,
+    mounts: Option<Mounts>,
+    subdomain: Option<String>
+}
+
+impl Snapshot {
+    fn take(req: &Request) -> Snapshot {
+        Snapshot {
+            url: req.url.clone(),
+            original_url: req.extensions.get::<OriginalUrl>().cloned(),
+            trail: req.extensions.get::<ForkTrail>().cloned(),
+            params: req.extensions.get::<RouteParams>().cloned(),
+            mounts: req.extensions.get::<MountPath>().cloned(),
+            subdomain: req.extensions.get::<Subdomain>().cloned()
+        }
+    }
+
+    fn restore(&self, req: &mut Request) {
+        req.url = self.url.clone();
+        restore_extension::<OriginalUrl>(req, &self.original_url);
+        restore_extension::<ForkTrail>(req, &self.trail);
+        restore_extension::<RouteParams>(req, &self.params);
+        restore_extension::<MountPath>(req, &self.mounts);
+        restore_extension::<Subdomain>(req, &self.subdomain);
+    }
+}
+
+fn restore_extension<K>(req: &mut Request, value: &Option<K::Value>)
+    where K: iron::typemap::Key,
+          K::Value: Clone
+{
+    match *value {
+        Some(ref value) => { req.extensions.insert::<K>(value.clone()); },
+        None            => { req.extensions.remove::<K>(); }
     }
 }
 
@@ -191,45 +877,138 @@ impl<P> Middleware for Fork<P>
 {
     /// Invokes the sub pipeline when the predicate P returns **true** for the request.
     fn process(&self, req: &mut Request, next: PipelineNext) -> IronResult<Response> {
-        let Fork(ref sub_pipeline, ref handler) = *self;
-        if handler.should_fork(req) {
-            handler.modify_request(req);
-            sub_pipeline.handle(req)
+        if !self.handler.should_fork(req) {
+            return next.process(req);
         }
-        else {
+
+        if !self.fallthrough {
+            req.extensions.entry::<ForkTrail>().or_insert_with(Vec::new).push(self.handler.describe());
+            self.handler.modify_request(req);
+            let mut result = self.pipeline.handle(req);
+            self.handler.after_fork(req, &mut result);
+            return result;
+        }
+
+        // Take a snapshot of the request so that it may be restored
+        // if the request falls through to the parent pipeline
+        let snapshot = Snapshot::take(req);
+        let fell_through = Cell::new(false);
+
+        req.extensions.entry::<ForkTrail>().or_insert_with(Vec::new).push(self.handler.describe());
+        self.handler.modify_request(req);
+        let mut result = self.pipeline.handle_or_else(req, &|req: &mut Request| {
+            fell_through.set(true);
+            snapshot.restore(req);
             next.process(req)
+        });
+        if !fell_through.get() {
+            self.handler.after_fork(req, &mut result);
+        }
+        result
+    }
+
+    fn describe(&self) -> MiddlewareDescription {
+        let mut description = MiddlewareDescription::new("Fork");
+        let mut detail = self.handler.describe();
+        if self.fallthrough {
+            detail.push_str(" fallthrough");
+            description = description.fallthrough();
+        }
+        if let Some(path) = self.handler.path() {
+            description = description.path(path);
+        }
+        description
+            .detail(detail)
+            .branch(None, self.pipeline.describe())
+    }
+
+    fn branch_mut(&mut self, label: Option<&str>) -> Option<&mut Pipeline> {
+        match label {
+            None    => Some(&mut self.pipeline),
+            Some(_) => None
         }
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::bool_assert_comparison)]
 mod tests {
 
     use super::parse_path;
-    use super::ParsePathError;
+    use super::PathErrorKind;
 
     #[test]
     fn parse_path_ok() {
         let path = parse_path("/this/is/the/path").unwrap();
-        assert_eq!(path, vec!["this", "is", "the", "path"]);
+        assert_eq!(path, vec![
+            Segment::Literal("this".to_string()),
+            Segment::Literal("is".to_string()),
+            Segment::Literal("the".to_string()),
+            Segment::Literal("path".to_string())
+        ]);
     }
 
     #[test]
     fn parse_path_strips_empty_segments() {
         let path = parse_path("/hello//world").unwrap();
-        assert_eq!(path, vec!["hello", "world"]);
+        assert_eq!(path, vec![Segment::Literal("hello".to_string()), Segment::Literal("world".to_string())]);
     }
 
     #[test]
     fn parse_path_require_leading_slash() {
         let path = parse_path("this/is/the/path");
-        assert_eq!(path, Err(ParsePathError::NoLeadingSlash));
+        assert_eq!(path.unwrap_err().kind(), PathErrorKind::NoLeadingSlash);
     }
 
     #[test]
     fn parse_path_require_non_empty() {
         let path = parse_path("/");
-        assert_eq!(path, Err(ParsePathError::PathEmpty));
+        assert_eq!(path.unwrap_err().kind(), PathErrorKind::PathEmpty);
+    }
+
+    use super::{match_path, capture_params, Params, Segment};
+
+    #[test]
+    fn parse_path_params() {
+        let path = parse_path("/tenants/:tenant_id/files/*rest").unwrap();
+        assert_eq!(path, vec![
+            Segment::Literal("tenants".to_string()),
+            Segment::Param("tenant_id".to_string()),
+            Segment::Literal("files".to_string()),
+            Segment::Wildcard("rest".to_string())
+        ]);
+    }
+
+    #[test]
+    fn parse_path_invalid_params() {
+        assert_eq!(parse_path("/a/:").unwrap_err().kind(), PathErrorKind::MissingName);
+        assert_eq!(parse_path("/:a/:a").unwrap_err().kind(), PathErrorKind::DuplicateName);
+        assert_eq!(parse_path("/*a/b").unwrap_err().kind(), PathErrorKind::WildcardNotLast);
+    }
+
+    #[test]
+    fn match_path_params() {
+        let pattern = parse_path("/tenants/:tenant_id/api").unwrap();
+        assert_eq!(match_path(&pattern, &["tenants", "acme", "api", "users"]), Some(3));
+        assert_eq!(match_path(&pattern, &["tenants", "acme", "www"]), None);
+        assert_eq!(match_path(&pattern, &["tenants", "acme"]), None);
+    }
+
+    #[test]
+    fn match_path_wildcard() {
+        let pattern = parse_path("/files/*rest").unwrap();
+        assert_eq!(match_path(&pattern, &["files", "a", "b.txt"]), Some(1));
+        assert_eq!(match_path(&pattern, &["files"]), Some(1));
+        assert_eq!(match_path(&pattern, &["images", "a.png"]), None);
+    }
+
+    #[test]
+    fn capture_params_decodes_values() {
+        let pattern = parse_path("/:name/*rest").unwrap();
+        let mut params = Params::default();
+        capture_params(&pattern, &["hello%20world", "a%2Fb", "c"], &mut params);
+        assert_eq!(params.get("name"), Some("hello world"));
+        assert_eq!(params.get("rest"), Some("a/b/c"));
     }
 
     use super::slice_starts_with;
@@ -238,27 +1017,27 @@ mod tests {
     fn slice_starts_with_detects_invalid_prefix() {
         let input  = ['1', '2', '3'];
         let prefix = ['9', '9', '9'];
-        assert_eq!(false, slice_starts_with(&input, &prefix));
+        assert_eq!(false, slice_starts_with(&input, &prefix, |a, b| a == b));
     }
 
     #[test]
     fn slice_starts_with_prefix_and_input_same_length() {
         let input  = ['1', '2', '3'];
         let prefix = ['1', '2', '3'];
-        assert_eq!(true, slice_starts_with(&input, &prefix));
+        assert_eq!(true, slice_starts_with(&input, &prefix, |a, b| a == b));
     }
 
     #[test]
     fn slice_starts_with_longer_input() {
         let input  = ['1', '2', '3', '4'];
         let prefix = ['1', '2', '3'];
-        assert_eq!(true, slice_starts_with(&input, &prefix));
+        assert_eq!(true, slice_starts_with(&input, &prefix, |a, b| a == b));
     }
 
     #[test]
     fn slice_starts_with_longer_prefix() {
         let input  = ['1', '2', '3'];
         let prefix = ['1', '2', '3', '4'];
-        assert_eq!(false, slice_starts_with(&input, &prefix));
+        assert_eq!(false, slice_starts_with(&input, &prefix, |a, b| a == b));
     }
 }
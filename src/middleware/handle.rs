@@ -1,6 +1,7 @@
 use iron::prelude::*;
 
 use {Middleware, PipelineNext};
+use describe::MiddlewareDescription;
 
 /// Container for a middleware function which must directly handle the request.
 ///
@@ -28,6 +29,10 @@ impl<F> Middleware for Handle<F>
     fn process(&self, req: &mut Request, _: PipelineNext) -> IronResult<Response> {
         (self.0)(req)
     }
+
+    fn describe(&self) -> MiddlewareDescription {
+        MiddlewareDescription::of::<Self>().terminal()
+    }
 }
 
 /// Container for a pipeline middleware function which may optionally invoke the
@@ -5,7 +5,21 @@
 //! All types which implement `iron::middleware::Handler` can also be used
 //! as iron-pipeline middleware. However because the `Handler` trait does
 //! not understand the concept of "next" middleware, it is generally only
-//! useful to put such handlers at the _end_ of a pipeline.
+//! useful to put such handlers at the _end_ of a pipeline. `Pipeline::build`
+//! reports any middleware registered after such a handler as unreachable.
 
+pub mod access_log;
+pub mod catch_panic;
+pub mod compress;
+pub mod cors;
+pub mod decompress;
+pub mod error_boundary;
 pub mod fork;
 pub mod handle;
+pub mod problem;
+pub mod request_id;
+pub mod router;
+pub mod split;
+pub mod switch;
+pub mod version;
+pub mod when;
@@ -0,0 +1,311 @@
+use iron::prelude::*;
+use iron::status;
+use iron::modifier::Modifier;
+
+use std::error;
+
+use {Middleware, PipelineNext, Error, json};
+use describe::MiddlewareDescription;
+use middleware::catch_panic::Panicked;
+use middleware::fork::OriginalUrl;
+use middleware::request_id::RequestId;
+use middleware::version::VersionError;
+
+/// An RFC 7807 "Problem Details" document describing an error.
+///
+/// May be used as a response modifier, which sets the status, the body (as
+/// `application/problem+json`) and the content type of the response.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate iron;
+/// # extern crate iron_pipeline;
+/// # use iron::prelude::*;
+/// # use iron::status;
+/// # use iron_pipeline::prelude::*;
+/// use iron_pipeline::middleware::problem::ProblemDetails;
+///
+/// # fn main() {
+/// # let mut pipeline = Pipeline::new();
+/// pipeline.add(Handle(|_| {
+///     let problem = ProblemDetails::new(status::Forbidden)
+///         .problem_type("https://example.com/problems/out-of-credit")
+///         .detail("Your current balance is 30, but that costs 50");
+///     Ok(Response::with(problem))
+/// }));
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProblemDetails {
+    /// A URI which identifies the problem type. Defaults to `about:blank`
+    pub problem_type: String,
+    /// A short summary of the problem type
+    pub title: String,
+    /// The HTTP status code
+    pub status: status::Status,
+    /// An explanation specific to this occurrence of the problem, if any
+    pub detail: Option<String>,
+    /// A URI which identifies this occurrence of the problem, if any
+    pub instance: Option<String>,
+    /// The ID of the request which caused the problem, if any.
+    /// Rendered as the `request_id` extension member
+    pub request_id: Option<String>
+}
+
+impl ProblemDetails {
+    /// Construct a new problem with the given status, titled with the
+    /// status' canonical reason (e.g. `Not Found`).
+    pub fn new(status: status::Status) -> ProblemDetails {
+        ProblemDetails {
+            problem_type: "about:blank".to_string(),
+            title: status.canonical_reason().unwrap_or("Unknown Error").to_string(),
+            status,
+            detail: None,
+            instance: None,
+            request_id: None
+        }
+    }
+
+    /// Describe an error raised while handling the request.
+    ///
+    /// Errors raised by this crate (such as `Error::NoHandler`) are given a
+    /// specific title and detail. Other errors are described by their `Display`
+    /// output, except for server errors, whose detail is omitted so that
+    /// internal details are not leaked to clients. The request ID assigned by
+    /// `AssignRequestId` is included, if any.
+    pub fn from_error(err: &IronError, req: &Request) -> ProblemDetails {
+        let status = err.response.status.unwrap_or(status::InternalServerError);
+        let mut problem = ProblemDetails::new(status);
+
+        if let Some(err) = err.error.downcast_ref::<Error>() {
+            #[allow(deprecated)]
+            let title = error::Error::description(err);
+            problem = problem.title(title).detail(match *err {
+                Error::NoHandler { ref method, ref url, .. } => format!("No handler for {} /{}", method, url.path().join("/"))
+            });
+        }
+        else if let Some(err) = err.error.downcast_ref::<VersionError>() {
+            problem = problem.detail(err.to_string());
+        }
+        else if err.error.downcast_ref::<Panicked>().is_some() {
+            // NOTE: Never include the panic message
+        }
+        else if !status.is_server_error() {
+            problem = problem.detail(err.error.to_string());
+        }
+
+        if let Some(id) = req.extensions.get::<RequestId>() {
+            problem = problem.request_id(id.clone());
+        }
+
+        let url = req.extensions.get::<OriginalUrl>().unwrap_or(&req.url);
+        problem.instance(format!("/{}", url.path().join("/")))
+    }
+
+    /// Set the problem type URI.
+    pub fn problem_type<S>(mut self, problem_type: S) -> ProblemDetails
+        where S: Into<String>
+    {
+        self.problem_type = problem_type.into();
+        self
+    }
+
+    /// Set the title.
+    pub fn title<S>(mut self, title: S) -> ProblemDetails
+        where S: Into<String>
+    {
+        self.title = title.into();
+        self
+    }
+
+    /// Set the detail.
+    pub fn detail<S>(mut self, detail: S) -> ProblemDetails
+        where S: Into<String>
+    {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// Set the instance URI.
+    pub fn instance<S>(mut self, instance: S) -> ProblemDetails
+        where S: Into<String>
+    {
+        self.instance = Some(instance.into());
+        self
+    }
+
+    /// Set the request ID.
+    pub fn request_id<S>(mut self, request_id: S) -> ProblemDetails
+        where S: Into<String>
+    {
+        self.request_id = Some(request_id.into());
+        self
+    }
+
+    /// Render the problem as an `application/problem+json` document.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        out.push_str("{\"type\":");
+        out.push_str(&json::string(&self.problem_type));
+        out.push_str(",\"title\":");
+        out.push_str(&json::string(&self.title));
+        out.push_str(",\"status\":");
+        out.push_str(&self.status.to_u16().to_string());
+        if let Some(ref detail) = self.detail {
+            out.push_str(",\"detail\":");
+            out.push_str(&json::string(detail));
+        }
+        if let Some(ref instance) = self.instance {
+            out.push_str(",\"instance\":");
+            out.push_str(&json::string(instance));
+        }
+        if let Some(ref request_id) = self.request_id {
+            out.push_str(",\"request_id\":");
+            out.push_str(&json::string(request_id));
+        }
+        out.push('}');
+        out
+    }
+
+    /// Render the problem as plain text, e.g. `404 Not Found: No handler for GET /users`,
+    /// followed by a `Request ID: ...` line if the request ID is set.
+    pub fn to_text(&self) -> String {
+        let mut text = match self.detail {
+            Some(ref detail) => format!("{} {}: {}\n", self.status.to_u16(), self.title, detail),
+            None             => format!("{} {}\n", self.status.to_u16(), self.title)
+        };
+        if let Some(ref request_id) = self.request_id {
+            text.push_str(&format!("Request ID: {}\n", request_id));
+        }
+        text
+    }
+
+    /// Replace the status, body and content type of the response with the
+    /// problem, rendered in the given format.
+    fn write_to(&self, response: &mut Response, format: Format) {
+        let (content_type, body) = match format {
+            Format::Json => ("application/problem+json", self.to_json()),
+            Format::Text => ("text/plain; charset=utf-8", self.to_text())
+        };
+        response.set_mut(self.status).set_mut(body);
+        response.headers.set_raw("Content-Type", vec![content_type.as_bytes().to_vec()]);
+    }
+}
+
+impl Modifier<Response> for ProblemDetails {
+    fn modify(self, response: &mut Response) {
+        self.write_to(response, Format::Json);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Json,
+    Text
+}
+
+/// Choose the format preferred by the request's `Accept` header. JSON is
+/// preferred, unless the client prefers plain text.
+fn negotiate(req: &Request) -> Format {
+    let values = match req.headers.get_raw("Accept") {
+        Some(values) => values,
+        None         => return Format::Json
+    };
+
+    let mut json_q = 0.0;
+    let mut text_q = 0.0;
+    for value in values {
+        for range in String::from_utf8_lossy(value).split(',') {
+            let mut parts = range.split(';');
+            let media_type = parts.next().unwrap_or("").trim().to_ascii_lowercase();
+            let q = parts
+                .filter_map(|p| p.trim().strip_prefix("q=").and_then(|q| q.parse::<f32>().ok()))
+                .next()
+                .unwrap_or(1.0);
+            match &media_type[..] {
+                "application/problem+json" | "application/json" | "application/*" => json_q = q.max(json_q),
+                "text/plain" | "text/*" => text_q = q.max(text_q),
+                "*/*" => {
+                    json_q = q.max(json_q);
+                    text_q = q.max(text_q);
+                },
+                _ => {}
+            }
+        }
+    }
+    if text_q > json_q { Format::Text } else { Format::Json }
+}
+
+/// Middleware which renders errors raised by the middleware after it as
+/// RFC 7807 Problem Details, in JSON or plain text as negotiated by the request's
+/// `Accept` header.
+///
+/// The error itself is still returned, so that outer middleware may log it,
+/// but its response is replaced.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate iron;
+/// # extern crate iron_pipeline;
+/// # use iron::prelude::*;
+/// # use iron::status;
+/// # use iron_pipeline::prelude::*;
+/// use iron_pipeline::middleware::problem::Problems;
+///
+/// # fn main() {
+/// # let mut pipeline = Pipeline::new();
+/// pipeline.add(Problems::new());
+/// pipeline.add(Router::new());
+/// // Requests not handled by the router raise `Error::NoHandler`, rendered as:
+/// // {"type":"about:blank","title":"Missing handler","status":500,"detail":"...","instance":"/path"}
+/// # }
+/// ```
+pub struct Problems {
+    map: Option<Box<ProblemMapFn>>
+}
+
+/// Function which customises the problem created for an error.
+type ProblemMapFn = dyn Fn(ProblemDetails, &IronError, &Request) -> ProblemDetails + Send + Sync;
+
+impl Problems {
+    /// Construct a new middleware which renders errors as Problem Details,
+    /// using `ProblemDetails::from_error`.
+    pub fn new() -> Problems {
+        Problems { map: None }
+    }
+
+    /// Customise the problem created for each error, e.g. to set the problem type.
+    pub fn map<F>(mut self, map: F) -> Problems
+        where F: Fn(ProblemDetails, &IronError, &Request) -> ProblemDetails + Send + Sync + 'static
+    {
+        self.map = Some(Box::new(map));
+        self
+    }
+}
+
+impl Default for Problems {
+    fn default() -> Problems {
+        Problems::new()
+    }
+}
+
+impl Middleware for Problems {
+    fn process(&self, req: &mut Request, next: PipelineNext) -> IronResult<Response> {
+        let mut err = match next.process(req) {
+            Ok(response) => return Ok(response),
+            Err(err)     => err
+        };
+        let mut problem = ProblemDetails::from_error(&err, req);
+        if let Some(ref map) = self.map {
+            problem = map(problem, &err, req);
+        }
+        problem.write_to(&mut err.response, negotiate(req));
+        Err(err)
+    }
+
+    fn describe(&self) -> MiddlewareDescription {
+        MiddlewareDescription::new("Problems")
+    }
+}
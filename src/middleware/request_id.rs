@@ -0,0 +1,192 @@
+use iron;
+use iron::prelude::*;
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use {Middleware, PipelineNext};
+use describe::MiddlewareDescription;
+
+/// Function which generates a new request ID.
+type GenerateFn = dyn Fn(&Request) -> String + Send + Sync;
+
+/// The longest request ID accepted from a client.
+const MAX_LENGTH: usize = 128;
+
+/// Middleware which assigns an ID to each request, so that log entries and
+/// errors may be correlated across services.
+///
+/// The ID is read from the `X-Request-Id` request header (or the configured header),
+/// or generated if the header is missing or invalid. Valid IDs are 1 to 128 characters
+/// long, and contain only ASCII letters, digits and `-`, `_`, `.`, `:`, `+`, `/` or `=`.
+/// Generated IDs look like UUIDs, e.g. `9b2f1c4e-07d3-4a58-b1e6-3f2a9c0d8e71`, but
+/// are not suitable for use as secrets.
+///
+/// The ID is stored in the `RequestId` request extension and echoed in the same
+/// header of the response, including error responses. `AccessLog`, `Problems` and
+/// `CatchPanic` include the ID in their output.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate iron;
+/// # extern crate iron_pipeline;
+/// # use iron::prelude::*;
+/// # use iron_pipeline::prelude::*;
+/// use iron_pipeline::middleware::request_id::{AssignRequestId, RequestId};
+///
+/// # fn main() {
+/// # let mut pipeline = Pipeline::new();
+/// pipeline.add(AssignRequestId::new());
+/// pipeline.add(Handle(|req| {
+///     let id = req.extensions.get::<RequestId>().unwrap();
+///     Ok(Response::with(format!("Handling request {}", id)))
+/// }));
+/// # }
+/// ```
+pub struct AssignRequestId {
+    header: String,
+    trust_incoming: bool,
+    generator: Option<Box<GenerateFn>>,
+    counter: AtomicUsize,
+    random: RandomState
+}
+
+/// Request extension containing the ID assigned by `AssignRequestId`.
+pub struct RequestId;
+impl iron::typemap::Key for RequestId {
+    type Value = String;
+}
+
+/// Test whether a request ID received from a client is acceptable.
+fn is_valid(id: &str) -> bool {
+    !id.is_empty() && id.len() <= MAX_LENGTH && id.bytes().all(|b| {
+        b.is_ascii_alphanumeric() || b"-_.:+/=".contains(&b)
+    })
+}
+
+impl AssignRequestId {
+    /// Construct a new middleware which uses the `X-Request-Id` header.
+    pub fn new() -> AssignRequestId {
+        AssignRequestId {
+            header: "X-Request-Id".to_string(),
+            trust_incoming: true,
+            generator: None,
+            counter: AtomicUsize::new(0),
+            random: RandomState::new()
+        }
+    }
+
+    /// Read and echo the ID in the given header, e.g. `X-Correlation-Id`.
+    pub fn header<S>(mut self, header: S) -> AssignRequestId
+        where S: Into<String>
+    {
+        self.header = header.into();
+        self
+    }
+
+    /// Always generate a new ID, ignoring any ID sent by the client.
+    /// Use this when the server is not behind a proxy which assigns IDs.
+    pub fn ignore_incoming(mut self) -> AssignRequestId {
+        self.trust_incoming = false;
+        self
+    }
+
+    /// Generate new IDs with the given function.
+    pub fn generator<F>(mut self, generator: F) -> AssignRequestId
+        where F: Fn(&Request) -> String + Send + Sync + 'static
+    {
+        self.generator = Some(Box::new(generator));
+        self
+    }
+
+    /// Read the ID sent by the client, if any and if valid.
+    fn incoming(&self, req: &Request) -> Option<String> {
+        if !self.trust_incoming {
+            return None;
+        }
+        let value = req.headers.get_raw(&self.header).and_then(|values| values.first())?;
+        let id = String::from_utf8_lossy(value).trim().to_string();
+        if is_valid(&id) {
+            Some(id)
+        }
+        else {
+            debug!("Ignoring invalid {} header", self.header);
+            None
+        }
+    }
+
+    /// Generate a new random ID, formatted as a version 4 UUID.
+    fn generate(&self, req: &Request) -> String {
+        if let Some(ref generator) = self.generator {
+            return generator(req);
+        }
+        let count = self.counter.fetch_add(1, Ordering::Relaxed);
+        let random = |salt: u8| {
+            let mut hasher = self.random.build_hasher();
+            hasher.write_usize(count);
+            hasher.write_u8(salt);
+            hasher.finish()
+        };
+        let (a, b) = (random(0), random(1));
+        format!("{:08x}-{:04x}-4{:03x}-{:04x}-{:012x}",
+            a >> 32,
+            (a >> 16) & 0xffff,
+            a & 0xfff,
+            (b >> 48) & 0x3fff | 0x8000,
+            b & 0xffff_ffff_ffff)
+    }
+}
+
+impl Default for AssignRequestId {
+    fn default() -> AssignRequestId {
+        AssignRequestId::new()
+    }
+}
+
+impl Middleware for AssignRequestId {
+    fn process(&self, req: &mut Request, next: PipelineNext) -> IronResult<Response> {
+        let id = match self.incoming(req) {
+            Some(id) => id,
+            None     => self.generate(req)
+        };
+        req.extensions.insert::<RequestId>(id.clone());
+
+        let mut result = next.process(req);
+
+        let response = match result {
+            Ok(ref mut response) => response,
+            Err(ref mut err)     => &mut err.response
+        };
+        response.headers.set_raw(self.header.clone(), vec![id.into_bytes()]);
+        result
+    }
+
+    fn describe(&self) -> MiddlewareDescription {
+        let detail = if self.trust_incoming {
+            self.header.clone()
+        }
+        else {
+            format!("{} (ignore incoming)", self.header)
+        };
+        MiddlewareDescription::new("AssignRequestId").detail(detail)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::is_valid;
+
+    #[test]
+    fn validate_ids() {
+        assert!(is_valid("abc-123"));
+        assert!(is_valid("9b2f1c4e-07d3-4a58-b1e6-3f2a9c0d8e71"));
+        assert!(is_valid("Root=1-5759e988-bd862e3fe1be46a994272793"));
+        assert!(!is_valid(""));
+        assert!(!is_valid("has space"));
+        assert!(!is_valid("line\nbreak"));
+        assert!(!is_valid(&"a".repeat(129)));
+    }
+}
@@ -0,0 +1,311 @@
+use iron::prelude::*;
+use iron::status;
+use iron::headers::Allow;
+use iron::method::Method;
+
+use std::collections::HashMap;
+
+use {Middleware, PipelineNext};
+use describe::{PipelineDescription, MiddlewareDescription};
+use middleware::fork::{Segment, PathError, Params, RouteParams, parse_path, decode};
+
+/// Middleware which dispatches requests to other middleware based on the
+/// request method and path.
+///
+/// Routes are stored in a trie of path segments, so a request is matched in time
+/// proportional to the length of its path rather than the number of routes.
+/// Paths use the same syntax as `Fork::when_path`: `:parameter` segments match
+/// any one segment, and a trailing `*wildcard` segment matches all remaining
+/// segments. Matched values are stored in the `RouteParams` request extension.
+/// When several routes match a path, routes which handle the request method are
+/// preferred, then literal segments over parameters, and parameters over wildcards.
+///
+/// Requests which do not match any route are passed to the next middleware.
+/// Requests which match a route but not its methods receive a
+/// `405 Method Not Allowed` response with an `Allow` header, and `OPTIONS`
+/// requests are answered automatically unless routed explicitly.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate iron;
+/// # extern crate iron_pipeline;
+/// # use iron::prelude::*;
+/// # use iron_pipeline::prelude::*;
+/// # use iron_pipeline::middleware::fork::RouteParams;
+/// # fn main() {
+/// # let mut pipeline = Pipeline::new();
+/// let mut router = Router::new();
+/// router.get("/users", Handle(|_| Ok(Response::with("All users"))));
+/// router.get("/users/:id", Handle(|req| {
+///     let id = req.extensions.get::<RouteParams>().unwrap().get("id").unwrap();
+///     Ok(Response::with(format!("User {}", id)))
+/// }));
+/// pipeline.add(router);
+/// # }
+/// ```
+pub struct Router {
+    root: Node,
+    routes: Vec<(Method, String)>
+}
+
+/// A node in the routing trie, matching a single path segment.
+#[derive(Default)]
+struct Node {
+    literals: HashMap<String, Node>,
+    param: Option<Box<Node>>,
+    wildcard: Option<Box<Node>>,
+    endpoints: HashMap<Method, Endpoint>
+}
+
+/// A middleware registered with a router, and the names of the
+/// parameters captured by its path (in order).
+struct Endpoint {
+    middleware: Box<dyn Middleware>,
+    names: Vec<String>
+}
+
+impl Node {
+    fn insert(&mut self, segments: &[Segment], method: Method, endpoint: Endpoint) {
+        let (segment, rest) = match segments.split_first() {
+            Some((segment, rest)) => (segment, rest),
+            None => {
+                self.endpoints.insert(method, endpoint);
+                return;
+            }
+        };
+        let node = match *segment {
+            Segment::Literal(ref literal) => self.literals.entry(literal.clone()).or_default(),
+            Segment::Param(_)             => self.param.get_or_insert_with(Box::default),
+            Segment::Wildcard(_)          => self.wildcard.get_or_insert_with(Box::default)
+        };
+        node.insert(rest, method, endpoint);
+    }
+
+    /// Find the endpoints which match the path, preferring literal segments over
+    /// parameters, and parameters over wildcards. If a method is given, only
+    /// endpoints which handle the method are matched.
+    fn find(&self, path: &[&str], method: Option<&Method>, captures: &mut Vec<String>) -> Option<&HashMap<Method, Endpoint>> {
+        let accepts = |endpoints: &HashMap<Method, Endpoint>| match method {
+            Some(method) => find_endpoint(endpoints, method).is_some(),
+            None         => !endpoints.is_empty()
+        };
+
+        let (segment, rest) = match path.split_first() {
+            Some((segment, rest)) => (segment, rest),
+            None => {
+                if accepts(&self.endpoints) {
+                    return Some(&self.endpoints);
+                }
+                // Wildcards may match zero segments
+                return self.wildcard.as_ref().filter(|wildcard| accepts(&wildcard.endpoints)).map(|wildcard| {
+                    captures.push(String::new());
+                    &wildcard.endpoints
+                });
+            }
+        };
+
+        if let Some(node) = self.literals.get(*segment) {
+            if let Some(endpoints) = node.find(rest, method, captures) {
+                return Some(endpoints);
+            }
+        }
+
+        // NOTE: Parameters must be non-empty, as with `Fork::when_path`
+        if let Some(node) = self.param.as_ref().filter(|_| !segment.is_empty()) {
+            captures.push(decode(segment));
+            if let Some(endpoints) = node.find(rest, method, captures) {
+                return Some(endpoints);
+            }
+            captures.pop();
+        }
+
+        self.wildcard.as_ref().filter(|wildcard| accepts(&wildcard.endpoints)).map(|wildcard| {
+            captures.push(decode(&path.join("/")));
+            &wildcard.endpoints
+        })
+    }
+
+    /// Find the node for the given route segments
+    fn lookup(&self, segments: &[Segment]) -> Option<&Node> {
+        let (segment, rest) = match segments.split_first() {
+            Some((segment, rest)) => (segment, rest),
+            None                  => return Some(self)
+        };
+        let node = match *segment {
+            Segment::Literal(ref literal) => self.literals.get(literal),
+            Segment::Param(_)             => self.param.as_deref(),
+            Segment::Wildcard(_)          => self.wildcard.as_deref()
+        };
+        node.and_then(|node| node.lookup(rest))
+    }
+}
+
+impl Router {
+    /// Construct a new router with no routes.
+    pub fn new() -> Router {
+        Router { root: Node::default(), routes: Vec::new() }
+    }
+
+    /// Route requests with the given method and path to the middleware,
+    /// replacing any middleware previously routed to the same method and path.
+    ///
+    /// #Panics
+    /// Panics when passed an invalid path string. Path should be of the form `/hello/:name`.
+    /// See `try_route` for a version which returns an error instead.
+    pub fn route<P, M>(&mut self, method: Method, path: P, middleware: M) -> &mut Router
+        where P: AsRef<str>,
+              M: Middleware + 'static
+    {
+        self.try_route(method, path, middleware).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Route requests with the given method and path to the middleware, as `route`.
+    /// Returns an error when passed an invalid path string.
+    pub fn try_route<P, M>(&mut self, method: Method, path: P, middleware: M) -> Result<&mut Router, PathError>
+        where P: AsRef<str>,
+              M: Middleware + 'static
+    {
+        let segments = parse_path(path.as_ref())?;
+        let names = segments.iter()
+            .filter_map(|segment| match *segment {
+                Segment::Literal(_)         => None,
+                Segment::Param(ref name)    |
+                Segment::Wildcard(ref name) => Some(name.clone())
+            })
+            .collect();
+
+        self.root.insert(&segments, method.clone(), Endpoint { middleware: Box::new(middleware), names });
+        self.routes.retain(|route| route.0 != method || route.1 != path.as_ref());
+        self.routes.push((method, path.as_ref().to_string()));
+        Ok(self)
+    }
+
+    /// Route `GET` requests with the given path to the middleware.
+    pub fn get<P, M>(&mut self, path: P, middleware: M) -> &mut Router
+        where P: AsRef<str>,
+              M: Middleware + 'static
+    {
+        self.route(Method::Get, path, middleware)
+    }
+
+    /// Route `POST` requests with the given path to the middleware.
+    pub fn post<P, M>(&mut self, path: P, middleware: M) -> &mut Router
+        where P: AsRef<str>,
+              M: Middleware + 'static
+    {
+        self.route(Method::Post, path, middleware)
+    }
+
+    /// Route `PUT` requests with the given path to the middleware.
+    pub fn put<P, M>(&mut self, path: P, middleware: M) -> &mut Router
+        where P: AsRef<str>,
+              M: Middleware + 'static
+    {
+        self.route(Method::Put, path, middleware)
+    }
+
+    /// Route `PATCH` requests with the given path to the middleware.
+    pub fn patch<P, M>(&mut self, path: P, middleware: M) -> &mut Router
+        where P: AsRef<str>,
+              M: Middleware + 'static
+    {
+        self.route(Method::Patch, path, middleware)
+    }
+
+    /// Route `DELETE` requests with the given path to the middleware.
+    pub fn delete<P, M>(&mut self, path: P, middleware: M) -> &mut Router
+        where P: AsRef<str>,
+              M: Middleware + 'static
+    {
+        self.route(Method::Delete, path, middleware)
+    }
+}
+
+impl Default for Router {
+    fn default() -> Router {
+        Router::new()
+    }
+}
+
+/// Find the endpoint for the method. HEAD requests are handled by the GET
+/// endpoint, if there is no HEAD endpoint.
+fn find_endpoint<'a>(endpoints: &'a HashMap<Method, Endpoint>, method: &Method) -> Option<&'a Endpoint> {
+    endpoints.get(method).or_else(|| {
+        if *method == Method::Head { endpoints.get(&Method::Get) } else { None }
+    })
+}
+
+/// List the methods allowed by a set of endpoints, in a stable order.
+fn allowed_methods(endpoints: &HashMap<Method, Endpoint>) -> Vec<Method> {
+    let mut methods: Vec<Method> = endpoints.keys().cloned().collect();
+    if endpoints.contains_key(&Method::Get) && !endpoints.contains_key(&Method::Head) {
+        methods.push(Method::Head);
+    }
+    if !endpoints.contains_key(&Method::Options) {
+        methods.push(Method::Options);
+    }
+    methods.sort_by(|a, b| a.as_ref().cmp(b.as_ref()));
+    methods
+}
+
+impl Middleware for Router {
+    fn process(&self, req: &mut Request, next: PipelineNext) -> IronResult<Response> {
+        let mut captures = Vec::new();
+        let endpoints = {
+            let mut path = req.url.path();
+            // Ignore a trailing slash, e.g. `/users/`
+            if path.last() == Some(&"") {
+                path.pop();
+            }
+            // NOTE: Prefer a route which handles the method, e.g. `DELETE /users/:id` over
+            // `GET /users/me` for `DELETE /users/me`, before falling back to any matching route
+            let endpoints = self.root.find(&path, Some(&req.method), &mut captures).or_else(|| {
+                captures.clear();
+                self.root.find(&path, None, &mut captures)
+            });
+            match endpoints {
+                Some(endpoints) => endpoints,
+                None            => return next.process(req)
+            }
+        };
+
+        let endpoint = match find_endpoint(endpoints, &req.method) {
+            Some(endpoint) => endpoint,
+            None => {
+                let status = if req.method == Method::Options { status::Ok } else { status::MethodNotAllowed };
+                let mut response = Response::with(status);
+                response.headers.set(Allow(allowed_methods(endpoints)));
+                return Ok(response);
+            }
+        };
+
+        // Capture any path parameters
+        {
+            let params = req.extensions.entry::<RouteParams>().or_insert_with(Params::default);
+            for (name, value) in endpoint.names.iter().zip(captures) {
+                params.insert(name.clone(), value);
+            }
+        }
+
+        endpoint.middleware.process(req, next)
+    }
+
+    fn describe(&self) -> MiddlewareDescription {
+        let mut description = MiddlewareDescription::new("Router")
+            .detail(format!("{} routes", self.routes.len()));
+
+        for (method, path) in &self.routes {
+            let segments = parse_path(path).expect("path is valid");
+            let endpoint = self.root.lookup(&segments).and_then(|node| node.endpoints.get(method));
+            if let Some(endpoint) = endpoint {
+                let pipeline = PipelineDescription {
+                    middlewares: vec![endpoint.middleware.describe()],
+                    fallback: "next".to_string()
+                };
+                description = description.branch(Some(format!("{} {}", method, path)), pipeline);
+            }
+        }
+        description
+    }
+}
@@ -0,0 +1,247 @@
+use iron;
+use iron::prelude::*;
+use iron::middleware::Handler;
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use {Pipeline, Middleware, PipelineNext};
+use describe::MiddlewareDescription;
+use middleware::fork::ForkTrail;
+
+/// Middleware which splits traffic between several sub pipelines by weight,
+/// e.g. to send 5% of requests to a canary release. Created by `Fork::split`.
+///
+/// Requests are assigned to a variant by hashing a sticky key, so each user
+/// sees the same variant on every request for as long as the variants and their
+/// weights are unchanged. The chosen variant is stored in the `SplitVariant`
+/// request extension, and may be added to the response as a header.
+///
+/// # Examples
+/// Send 10% of users to a canary pipeline, remembering the assignment in a cookie:
+///
+/// ```rust
+/// # extern crate iron;
+/// # extern crate iron_pipeline;
+/// # use iron::prelude::*;
+/// # use iron_pipeline::prelude::*;
+/// use iron_pipeline::middleware::split::Sticky;
+///
+/// # fn main() {
+/// # let mut pipeline = Pipeline::new();
+/// pipeline.add(Fork::split(Sticky::Cookie("canary".to_string()))
+///     .variant("stable", 90, |stable| {
+///         stable.add(Handle(|_| Ok(Response::with("Hello from iron-pipeline"))));
+///     })
+///     .variant("canary", 10, |canary| {
+///         canary.add(Handle(|_| Ok(Response::with("Hello from the canary"))));
+///     })
+///     .response_header("X-Variant"));
+/// # }
+/// ```
+pub struct Split {
+    sticky: Sticky,
+    variants: Vec<Variant>,
+    response_header: Option<String>,
+    cookie_max_age: u32,
+    counter: AtomicUsize,
+    random: RandomState
+}
+
+struct Variant {
+    name: String,
+    weight: u32,
+    pipeline: Pipeline
+}
+
+/// Function which extracts a sticky key from a request.
+pub type StickyFn = dyn Fn(&Request) -> Option<String> + Send + Sync;
+
+/// The request attribute used to assign requests to a variant.
+pub enum Sticky {
+    /// Hash the value of the named cookie. Requests without the cookie are
+    /// assigned a random value, which is set as a cookie on the response.
+    /// The cookie expires after 30 days by default (see `Split::cookie_max_age`).
+    Cookie(String),
+    /// Hash the value of the named request header. Requests without the
+    /// header are assigned a variant at random.
+    Header(String),
+    /// Hash the value returned by the function, e.g. a user or tenant id.
+    /// Requests for which the function returns `None` are assigned a variant at random.
+    Attribute(Box<StickyFn>),
+    /// Assign every request a variant at random.
+    None
+}
+
+/// Request extension containing the name of the variant chosen by a `Split`.
+pub struct SplitVariant;
+impl iron::typemap::Key for SplitVariant {
+    type Value = String;
+}
+
+impl Sticky {
+    fn describe(&self) -> String {
+        match *self {
+            Sticky::Cookie(ref name) => format!("cookie({})", name),
+            Sticky::Header(ref name) => format!("header({})", name),
+            Sticky::Attribute(_)     => "<fn>".to_string(),
+            Sticky::None             => "random".to_string()
+        }
+    }
+}
+
+impl Split {
+    /// Construct a new split with no variants. See `Fork::split`.
+    pub fn new(sticky: Sticky) -> Split {
+        Split {
+            sticky,
+            variants: Vec::new(),
+            response_header: None,
+            cookie_max_age: 30 * 24 * 60 * 60,
+            counter: AtomicUsize::new(0),
+            random: RandomState::new()
+        }
+    }
+
+    /// Add a variant which receives `weight` parts of the traffic. For example,
+    /// a variant with a weight of 10 receives 10% of requests if the weights of
+    /// all variants add up to 100. The `pipeline_builder` is used to construct
+    /// the sub pipeline, and is executed immediately.
+    pub fn variant<N, B>(mut self, name: N, weight: u32, pipeline_builder: B) -> Split
+        where N: Into<String>,
+              B: FnOnce(&mut Pipeline)
+    {
+        let mut pipeline = Pipeline::new();
+        pipeline_builder(&mut pipeline);
+        self.variants.push(Variant { name: name.into(), weight, pipeline });
+        self
+    }
+
+    /// Add a header containing the name of the chosen variant to each response.
+    pub fn response_header<H>(mut self, name: H) -> Split
+        where H: Into<String>
+    {
+        self.response_header = Some(name.into());
+        self
+    }
+
+    /// Set the number of seconds for which browsers keep the cookie set by
+    /// `Sticky::Cookie`, and so keep users on the same variant. Defaults to 30 days.
+    pub fn cookie_max_age(mut self, seconds: u32) -> Split {
+        self.cookie_max_age = seconds;
+        self
+    }
+
+    /// Generate a random value for requests without a sticky key.
+    fn random_key(&self) -> String {
+        let mut hasher = self.random.build_hasher();
+        hasher.write_usize(self.counter.fetch_add(1, Ordering::Relaxed));
+        format!("{:016x}", hasher.finish())
+    }
+
+    /// Choose a variant for the key, by weight.
+    fn choose(&self, key: &str) -> Option<&Variant> {
+        let total: u64 = self.variants.iter().map(|v| u64::from(v.weight)).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut bucket = fnv1a(key.as_bytes()) % total;
+        for variant in &self.variants {
+            if bucket < u64::from(variant.weight) {
+                return Some(variant);
+            }
+            bucket -= u64::from(variant.weight);
+        }
+        None
+    }
+}
+
+/// Get the value of the named cookie from the request.
+fn request_cookie(req: &Request, name: &str) -> Option<String> {
+    let values = req.headers.get_raw("Cookie")?;
+    for value in values {
+        for pair in String::from_utf8_lossy(value).split(';') {
+            let mut parts = pair.trim().splitn(2, '=');
+            if parts.next() == Some(name) {
+                return parts.next().map(|value| value.to_string());
+            }
+        }
+    }
+    None
+}
+
+/// 64 bit FNV-1a hash, which (unlike the standard library hasher) is stable
+/// across processes, so assignments survive restarts and are shared by replicas.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+impl Middleware for Split {
+    /// Invokes the sub pipeline of the variant chosen for the request.
+    fn process(&self, req: &mut Request, next: PipelineNext) -> IronResult<Response> {
+        // The cookie to set on the response, if the request is assigned a new key
+        let mut new_cookie = None;
+
+        let key = match self.sticky {
+            Sticky::Cookie(ref name) => request_cookie(req, name).or_else(|| {
+                let value = self.random_key();
+                new_cookie = Some(format!("{}={}; Path=/; Max-Age={}", name, value, self.cookie_max_age));
+                Some(value)
+            }),
+            Sticky::Header(ref name) => {
+                req.headers.get_raw(name).and_then(|values| values.first())
+                    .map(|value| String::from_utf8_lossy(value).into_owned())
+            },
+            Sticky::Attribute(ref f) => f(req),
+            Sticky::None             => None
+        };
+        let key = key.unwrap_or_else(|| self.random_key());
+
+        let variant = match self.choose(&key) {
+            Some(variant) => variant,
+            None          => return next.process(req)
+        };
+
+        req.extensions.insert::<SplitVariant>(variant.name.clone());
+        req.extensions.entry::<ForkTrail>().or_insert_with(Vec::new)
+            .push(format!("split({}) = {}", self.sticky.describe(), variant.name));
+
+        let mut result = variant.pipeline.handle(req);
+        {
+            let response = match result {
+                Ok(ref mut response) => response,
+                Err(ref mut err)     => &mut err.response
+            };
+            if let Some(ref header) = self.response_header {
+                response.headers.set_raw(header.clone(), vec![variant.name.clone().into_bytes()]);
+            }
+            if let Some(cookie) = new_cookie {
+                response.headers.append_raw("Set-Cookie", cookie.into_bytes());
+            }
+        }
+        result
+    }
+
+    fn describe(&self) -> MiddlewareDescription {
+        let weights: Vec<String> = self.variants.iter()
+            .map(|v| format!("{}={}", v.name, v.weight))
+            .collect();
+        let mut description = MiddlewareDescription::new("Split")
+            .detail(format!("{}: {}", self.sticky.describe(), weights.join(", ")));
+        for variant in &self.variants {
+            description = description.branch(Some(variant.name.clone()), variant.pipeline.describe());
+        }
+        description
+    }
+
+    fn branch_mut(&mut self, label: Option<&str>) -> Option<&mut Pipeline> {
+        let label = label?;
+        self.variants.iter_mut().find(|v| v.name == label).map(|v| &mut v.pipeline)
+    }
+}
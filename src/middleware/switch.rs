@@ -0,0 +1,186 @@
+use iron::prelude::*;
+use iron::middleware::Handler;
+
+use std::collections::HashMap;
+
+use {Pipeline, Middleware, PipelineNext};
+use describe::MiddlewareDescription;
+use middleware::fork::{ForkTrail, strip_prefix, decode};
+use predicate::request_host;
+
+/// Middleware which delegates to one of many sub pipelines, selected by
+/// a key extracted from each request.
+///
+/// Sub pipelines are stored in a `HashMap`, so the cost of selecting a pipeline
+/// does not depend on the number of cases, and the order in which cases are
+/// registered does not matter. Requests with no key, or with a key which has
+/// no case, are passed to the next middleware.
+///
+/// # Examples
+/// A sub pipeline for each tenant, selected by the first path segment:
+///
+/// ```rust
+/// # extern crate iron;
+/// # extern crate iron_pipeline;
+/// # use iron::prelude::*;
+/// # use iron_pipeline::prelude::*;
+/// use iron_pipeline::middleware::switch::PathSegment;
+///
+/// # fn main() {
+/// # let mut pipeline = Pipeline::new();
+/// let mut tenants = Switch::new(PathSegment);
+/// for tenant in &["acme", "globex", "initech"] {
+///     tenants.case(*tenant, |sub_pipeline| {
+///         sub_pipeline.add(Handle(move |_| Ok(Response::with(format!("Hello, {}", tenant)))));
+///     });
+/// }
+/// pipeline.add(tenants);
+/// # }
+/// ```
+pub struct Switch<K> {
+    key: K,
+    cases: HashMap<String, Pipeline>
+}
+
+/// Trait which extracts the key used by a `Switch` to select a sub pipeline.
+///
+/// Implemented for all functions and closures of the form `Fn(&Request) -> Option<String>`.
+pub trait SwitchKey {
+    /// Extract the key from the request, if any.
+    fn key(&self, req: &Request) -> Option<String>;
+
+    /// Modify the request before it is passed to the selected sub pipeline,
+    /// e.g. to strip a path prefix. Does nothing by default.
+    fn modify_request(&self, _: &mut Request) {
+        // nop
+    }
+
+    /// Describe the key for `Pipeline::describe`, e.g. `path_segment`.
+    fn describe(&self) -> String {
+        "<fn>".to_string()
+    }
+}
+
+impl<F> SwitchKey for F
+    where F: Fn(&Request) -> Option<String>
+{
+    fn key(&self, req: &Request) -> Option<String> {
+        self(req)
+    }
+}
+
+/// Selects a sub pipeline by the first segment of the request path, which
+/// is stripped from the request URL (as with `Fork::when_path`).
+pub struct PathSegment;
+
+impl SwitchKey for PathSegment {
+    fn key(&self, req: &Request) -> Option<String> {
+        req.url.path().first().filter(|s| !s.is_empty()).map(|s| decode(s))
+    }
+
+    fn modify_request(&self, req: &mut Request) {
+        strip_prefix(req, 1);
+    }
+
+    fn describe(&self) -> String {
+        "path_segment".to_string()
+    }
+}
+
+/// Selects a sub pipeline by the value of the named request header.
+pub struct HeaderValue<N>(pub N);
+
+impl<N> SwitchKey for HeaderValue<N>
+    where N: AsRef<str>
+{
+    fn key(&self, req: &Request) -> Option<String> {
+        req.headers.get_raw(self.0.as_ref())
+            .and_then(|values| values.first())
+            .map(|value| String::from_utf8_lossy(value).trim().to_string())
+    }
+
+    fn describe(&self) -> String {
+        format!("header({})", self.0.as_ref())
+    }
+}
+
+/// Selects a sub pipeline by the (lower case) hostname of the request.
+pub struct HostName;
+
+impl SwitchKey for HostName {
+    fn key(&self, req: &Request) -> Option<String> {
+        let (hostname, _) = request_host(req);
+        Some(hostname.trim_end_matches('.').to_ascii_lowercase())
+    }
+
+    fn describe(&self) -> String {
+        "host".to_string()
+    }
+}
+
+impl<K> Switch<K>
+    where K: SwitchKey
+{
+    /// Construct a new switch with no cases, using `key` to select
+    /// a sub pipeline for each request.
+    pub fn new(key: K) -> Switch<K> {
+        Switch { key, cases: HashMap::new() }
+    }
+
+    /// Delegate requests with the given key to a sub pipeline, replacing any
+    /// sub pipeline previously registered for the same key. The `pipeline_builder`
+    /// is used to construct the sub pipeline, and is executed immediately.
+    pub fn case<S, B>(&mut self, key: S, pipeline_builder: B) -> &mut Switch<K>
+        where S: Into<String>,
+              B: FnOnce(&mut Pipeline)
+    {
+        let mut sub_pipeline = Pipeline::new();
+        pipeline_builder(&mut sub_pipeline);
+        self.cases.insert(key.into(), sub_pipeline);
+        self
+    }
+
+    /// Get the sub pipeline registered for the given key, if any.
+    pub fn case_mut(&mut self, key: &str) -> Option<&mut Pipeline> {
+        self.cases.get_mut(key)
+    }
+}
+
+impl<K> Middleware for Switch<K>
+    where K: SwitchKey + Send + Sync
+{
+    /// Invokes the sub pipeline registered for the request's key, if any.
+    fn process(&self, req: &mut Request, next: PipelineNext) -> IronResult<Response> {
+        let key = match self.key.key(req) {
+            Some(key) => key,
+            None      => return next.process(req)
+        };
+
+        let pipeline = match self.cases.get(&key) {
+            Some(pipeline) => pipeline,
+            None           => return next.process(req)
+        };
+
+        let trail = format!("switch({}) = {}", self.key.describe(), key);
+        req.extensions.entry::<ForkTrail>().or_insert_with(Vec::new).push(trail);
+        self.key.modify_request(req);
+        pipeline.handle(req)
+    }
+
+    fn describe(&self) -> MiddlewareDescription {
+        let mut description = MiddlewareDescription::new("Switch")
+            .detail(format!("{} ({} cases)", self.key.describe(), self.cases.len()));
+
+        // NOTE: Sort the cases so that descriptions are stable
+        let mut keys: Vec<&String> = self.cases.keys().collect();
+        keys.sort();
+        for key in keys {
+            description = description.branch(Some(key.clone()), self.cases[key].describe());
+        }
+        description
+    }
+
+    fn branch_mut(&mut self, label: Option<&str>) -> Option<&mut Pipeline> {
+        label.and_then(move |label| self.cases.get_mut(label))
+    }
+}
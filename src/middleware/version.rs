@@ -0,0 +1,308 @@
+use iron;
+use iron::prelude::*;
+use iron::status;
+use iron::middleware::Handler;
+
+use url::form_urlencoded;
+
+use std::error;
+use std::fmt;
+
+use {Pipeline, Middleware, PipelineNext};
+use describe::MiddlewareDescription;
+use middleware::fork::{ForkTrail, strip_prefix};
+
+/// Middleware which delegates to a sub pipeline for each version of an API,
+/// selected by the version requested by the client.
+///
+/// The requested version is read using each of the configured strategies in
+/// turn, and the resolved version is stored in the `ApiVersion` request extension.
+/// Requests which do not specify a version use the default version, if any, and
+/// are otherwise passed to the next middleware. Requests for an unknown version
+/// fail with a `VersionError` and a `400 Bad Request` response.
+///
+/// # Examples
+/// Version 2 of an API, requested via either a header or a path prefix such as `/v2`,
+/// and a deprecated version 1:
+///
+/// ```rust
+/// # extern crate iron;
+/// # extern crate iron_pipeline;
+/// # use iron::prelude::*;
+/// # use iron_pipeline::prelude::*;
+/// use iron_pipeline::middleware::version::VersionStrategy;
+///
+/// # fn main() {
+/// # let mut pipeline = Pipeline::new();
+/// pipeline.add(Versioned::new()
+///     .strategy(VersionStrategy::Header("X-ApiVersion".to_string()))
+///     .strategy(VersionStrategy::PathSegment("v".to_string()))
+///     .version("1", |v1| {
+///         v1.add(Handle(|_| Ok(Response::with("Hello from v1"))));
+///     })
+///     .version("2", |v2| {
+///         v2.add(Handle(|_| Ok(Response::with("Hello from v2"))));
+///     })
+///     .deprecated("1", Some("Sat, 01 Jan 2028 00:00:00 GMT"))
+///     .default_version("2"));
+/// # }
+/// ```
+pub struct Versioned {
+    strategies: Vec<VersionStrategy>,
+    versions: Vec<Version>,
+    default_version: Option<String>
+}
+
+struct Version {
+    name: String,
+    pipeline: Pipeline,
+    deprecated: bool,
+    sunset: Option<String>
+}
+
+/// A way of reading the API version requested by a client.
+#[derive(Debug, Clone, PartialEq)]
+pub enum VersionStrategy {
+    /// The value of the named request header, e.g. `X-ApiVersion: 2`
+    Header(String),
+    /// The value of the named query string parameter, e.g. `?api-version=2`
+    Query(String),
+    /// The first path segment, after the given prefix, e.g. `/v2/users` with a prefix of `v`.
+    /// The rest of the segment must be a registered version, or look like a version
+    /// (starting with a digit, e.g. `2` or `1.1`); other segments are ignored, so
+    /// `/videos` is not read as version `ideos`. The segment is stripped from the
+    /// request URL (as with `Fork::when_path`).
+    PathSegment(String),
+    /// The named parameter of the `Accept` header media type,
+    /// e.g. `Accept: application/json; version=2`
+    MediaType(String)
+}
+
+impl fmt::Display for VersionStrategy {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            VersionStrategy::Header(ref name)        => write!(fmt, "header({})", name),
+            VersionStrategy::Query(ref name)         => write!(fmt, "query({})", name),
+            VersionStrategy::PathSegment(ref prefix) => write!(fmt, "path(/{}*)", prefix),
+            VersionStrategy::MediaType(ref name)     => write!(fmt, "media_type({})", name)
+        }
+    }
+}
+
+impl VersionStrategy {
+    /// Read the requested version from the request, if present.
+    fn read(&self, req: &Request) -> Option<String> {
+        match *self {
+            VersionStrategy::Header(ref name) => {
+                req.headers.get_raw(name).and_then(|values| values.first())
+                    .map(|value| String::from_utf8_lossy(value).trim().to_string())
+            },
+            VersionStrategy::Query(ref name) => {
+                let query = req.url.query()?;
+                form_urlencoded::parse(query.as_bytes())
+                    .find(|pair| pair.0 == name.as_str())
+                    .map(|pair| pair.1.into_owned())
+            },
+            VersionStrategy::PathSegment(ref prefix) => {
+                let path = req.url.path();
+                let segment = path.first().filter(|s| s.len() > prefix.len())?;
+                if segment.starts_with(prefix.as_str()) { Some(segment[prefix.len()..].to_string()) } else { None }
+            },
+            VersionStrategy::MediaType(ref name) => {
+                let values = req.headers.get_raw("Accept")?;
+                for value in values {
+                    let value = String::from_utf8_lossy(value);
+                    if let Some(version) = value.split(',').filter_map(|m| media_type_param(m, name)).next() {
+                        return Some(version);
+                    }
+                }
+                None
+            }
+        }
+    }
+}
+
+/// Test whether a path segment looks like a version, e.g. `2`, `1.1` or `2009-01-01`.
+fn looks_like_version(segment: &str) -> bool {
+    segment.starts_with(|c: char| c.is_ascii_digit())
+        && segment.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+}
+
+/// Get the value of the named parameter of a media type,
+/// e.g. `2` for `application/json; version="2"`.
+fn media_type_param(media_type: &str, name: &str) -> Option<String> {
+    media_type.split(';').skip(1).filter_map(|param| {
+        let mut parts = param.splitn(2, '=');
+        let key = parts.next()?.trim();
+        let value = parts.next()?.trim().trim_matches('"');
+        if key.eq_ignore_ascii_case(name) { Some(value.to_string()) } else { None }
+    }).next()
+}
+
+/// Request extension containing the API version resolved by `Versioned`.
+pub struct ApiVersion;
+impl iron::typemap::Key for ApiVersion {
+    type Value = String;
+}
+
+/// Error raised when a client requests an API version which is not supported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionError {
+    /// The version requested by the client
+    pub requested: String,
+    /// The supported versions, in the order in which they were registered
+    pub supported: Vec<String>
+}
+
+impl fmt::Display for VersionError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        #[allow(deprecated)]
+        let description = error::Error::description(self);
+        write!(fmt, "{} \"{}\" (supported versions: {})", description, self.requested, self.supported.join(", "))
+    }
+}
+
+impl error::Error for VersionError {
+    fn description(&self) -> &'static str {
+        "Unsupported API version"
+    }
+}
+
+impl Versioned {
+    /// Construct a new versioning middleware, with no strategies or versions.
+    pub fn new() -> Versioned {
+        Versioned { strategies: Vec::new(), versions: Vec::new(), default_version: None }
+    }
+
+    /// Add a strategy used to read the requested version.
+    /// Strategies are tried in the order in which they were added.
+    pub fn strategy(mut self, strategy: VersionStrategy) -> Versioned {
+        self.strategies.push(strategy);
+        self
+    }
+
+    /// Add a version of the API, replacing any sub pipeline previously added for the same
+    /// version. The `pipeline_builder` is used to construct the sub pipeline, and is
+    /// executed immediately.
+    pub fn version<N, B>(mut self, name: N, pipeline_builder: B) -> Versioned
+        where N: Into<String>,
+              B: FnOnce(&mut Pipeline)
+    {
+        let name = name.into();
+        let mut pipeline = Pipeline::new();
+        pipeline_builder(&mut pipeline);
+        match self.versions.iter_mut().find(|v| v.name == name) {
+            Some(version) => version.pipeline = pipeline,
+            None          => self.versions.push(Version { name, pipeline, deprecated: false, sunset: None })
+        }
+        self
+    }
+
+    /// Mark a version as deprecated. Responses from the version include a
+    /// `Deprecation` header, and a `Sunset` header containing the HTTP date
+    /// after which the version will be removed, if given.
+    ///
+    /// #Panics
+    /// Panics if the version has not been added.
+    pub fn deprecated<N>(mut self, name: N, sunset: Option<&str>) -> Versioned
+        where N: AsRef<str>
+    {
+        {
+            let version = self.versions.iter_mut().find(|v| v.name == name.as_ref())
+                .unwrap_or_else(|| panic!("Unknown API version \"{}\"", name.as_ref()));
+            version.deprecated = true;
+            version.sunset = sunset.map(|s| s.to_string());
+        }
+        self
+    }
+
+    /// Use the version for requests which do not specify a version.
+    pub fn default_version<N>(mut self, name: N) -> Versioned
+        where N: Into<String>
+    {
+        self.default_version = Some(name.into());
+        self
+    }
+
+    /// Read the requested version, and the strategy which found it.
+    fn requested(&self, req: &Request) -> Option<(String, &VersionStrategy)> {
+        self.strategies.iter()
+            .filter_map(|s| s.read(req).map(|v| (v, s)))
+            .find(|&(ref version, strategy)| match *strategy {
+                VersionStrategy::PathSegment(_) => self.versions.iter().any(|v| v.name == *version) || looks_like_version(version),
+                _                               => true
+            })
+    }
+}
+
+impl Default for Versioned {
+    fn default() -> Versioned {
+        Versioned::new()
+    }
+}
+
+impl Middleware for Versioned {
+    /// Invokes the sub pipeline for the requested version.
+    fn process(&self, req: &mut Request, next: PipelineNext) -> IronResult<Response> {
+        let (name, strategy) = match self.requested(req) {
+            Some((name, strategy)) => (name, Some(strategy)),
+            None => match self.default_version {
+                Some(ref name) => (name.clone(), None),
+                None           => return next.process(req)
+            }
+        };
+
+        let version = match self.versions.iter().find(|v| v.name == name) {
+            Some(version) => version,
+            None => {
+                let err = VersionError {
+                    requested: name,
+                    supported: self.versions.iter().map(|v| v.name.clone()).collect()
+                };
+                let message = err.to_string();
+                return Err(IronError::new(err, (status::BadRequest, message)));
+            }
+        };
+
+        if let Some(&VersionStrategy::PathSegment(_)) = strategy {
+            strip_prefix(req, 1);
+        }
+        req.extensions.insert::<ApiVersion>(version.name.clone());
+        req.extensions.entry::<ForkTrail>().or_insert_with(Vec::new).push(format!("version({})", version.name));
+
+        let mut result = version.pipeline.handle(req);
+        if version.deprecated {
+            let response = match result {
+                Ok(ref mut response) => response,
+                Err(ref mut err)     => &mut err.response
+            };
+            response.headers.set_raw("Deprecation", vec![b"true".to_vec()]);
+            if let Some(ref sunset) = version.sunset {
+                response.headers.set_raw("Sunset", vec![sunset.clone().into_bytes()]);
+            }
+        }
+        result
+    }
+
+    fn describe(&self) -> MiddlewareDescription {
+        let strategies: Vec<String> = self.strategies.iter().map(|s| s.to_string()).collect();
+        let mut detail = strategies.join(", ");
+        if let Some(ref name) = self.default_version {
+            detail.push_str(&format!(" default={}", name));
+        }
+        let deprecated: Vec<&str> = self.versions.iter().filter(|v| v.deprecated).map(|v| &v.name[..]).collect();
+        if !deprecated.is_empty() {
+            detail.push_str(&format!(" deprecated={}", deprecated.join(",")));
+        }
+        let mut description = MiddlewareDescription::new("Versioned").detail(detail);
+        for version in &self.versions {
+            description = description.branch(Some(version.name.clone()), version.pipeline.describe());
+        }
+        description
+    }
+
+    fn branch_mut(&mut self, label: Option<&str>) -> Option<&mut Pipeline> {
+        let label = label?;
+        self.versions.iter_mut().find(|v| v.name == label).map(|v| &mut v.pipeline)
+    }
+}
@@ -0,0 +1,66 @@
+use iron::prelude::*;
+
+use {Pipeline, Middleware, PipelineNext};
+use describe::MiddlewareDescription;
+use predicate::Predicate;
+
+/// Middleware which invokes the wrapped middleware only when the request
+/// matches the predicate, and otherwise invokes the next middleware.
+///
+/// Unlike `Fork`, the wrapped middleware's "next" middleware is the next
+/// middleware in the parent pipeline, so requests rejoin the parent pipeline
+/// when the wrapped middleware passes them on.
+///
+/// # Examples
+/// Require authentication only for requests to "/admin", while sharing all
+/// other middleware:
+///
+/// ```rust
+/// # extern crate iron;
+/// # extern crate iron_pipeline;
+/// # use iron::prelude::*;
+/// # use iron::status;
+/// # use iron_pipeline::prelude::*;
+/// # fn is_authenticated(_: &Request) -> bool { true }
+/// # fn main() {
+/// # let mut pipeline = Pipeline::new();
+/// let is_admin = |req: &Request| req.url.path()[0] == "admin";
+/// pipeline.add(When(is_admin, HandleNext(|req, next| {
+///     if is_authenticated(req) { next.process(req) } else { Ok(Response::with(status::Unauthorized)) }
+/// })));
+/// pipeline.add(Handle(|_| Ok(Response::with("Hello from iron-pipeline"))));
+/// # }
+/// ```
+pub struct When<P, M>(pub P, pub M);
+
+impl<P, M> Middleware for When<P, M>
+    where P: Predicate,
+          M: Middleware
+{
+    fn process(&self, req: &mut Request, next: PipelineNext) -> IronResult<Response> {
+        if self.0.matches(req) {
+            self.1.process(req, next)
+        }
+        else {
+            next.process(req)
+        }
+    }
+
+    /// Describes the wrapped middleware, including its nested pipelines (which
+    /// are also the pipelines reached by `branch_mut`).
+    fn describe(&self) -> MiddlewareDescription {
+        let inner = self.1.describe();
+        let detail = match inner.detail {
+            Some(ref detail) => format!("{} => {} {}", self.0.describe(), inner.kind, detail),
+            None             => format!("{} => {}", self.0.describe(), inner.kind)
+        };
+        let mut description = MiddlewareDescription::new("When").detail(detail).fallthrough();
+        description.path = inner.path;
+        description.branches = inner.branches;
+        description
+    }
+
+    fn branch_mut(&mut self, label: Option<&str>) -> Option<&mut Pipeline> {
+        self.1.branch_mut(label)
+    }
+}
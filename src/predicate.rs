@@ -0,0 +1,339 @@
+//! Composable request predicates for use with `Fork::when_matches`.
+//!
+//! Predicates may be combined with `and`, `or` and `not`, and describe
+//! themselves in the output of `Pipeline::describe`.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # extern crate iron;
+//! # extern crate iron_pipeline;
+//! # use iron::prelude::*;
+//! # use iron::method::Post;
+//! # use iron_pipeline::prelude::*;
+//! use iron_pipeline::predicate::{Predicate, Method, Header, ContentType};
+//!
+//! # fn main() {
+//! # let mut pipeline = Pipeline::new();
+//! let predicate = Method(Post)
+//!     .and(ContentType("application/json"))
+//!     .and(Header("X-ApiVersion", "2009-01-01").not());
+//!
+//! pipeline.add(Fork::when_matches(predicate, |posts| {
+//!     posts.add(Handle(|_| Ok(Response::with("Hello from iron-pipeline"))));
+//! }));
+//! # }
+//! ```
+
+use iron::prelude::*;
+use iron::method;
+use iron::headers;
+
+use url::form_urlencoded;
+
+/// Trait which defines a test applied to each request.
+///
+/// Implemented for all functions and closures of the form `Fn(&Request) -> bool`.
+pub trait Predicate: Send + Sync {
+    /// Test the request.
+    fn matches(&self, req: &Request) -> bool;
+
+    /// Describe this predicate for `Pipeline::describe`.
+    fn describe(&self) -> String {
+        "<fn>".to_string()
+    }
+
+    /// Match requests which match both this predicate and `other`.
+    fn and<P>(self, other: P) -> And<Self, P>
+        where Self: Sized,
+              P: Predicate
+    {
+        And(self, other)
+    }
+
+    /// Match requests which match either this predicate or `other`.
+    fn or<P>(self, other: P) -> Or<Self, P>
+        where Self: Sized,
+              P: Predicate
+    {
+        Or(self, other)
+    }
+
+    /// Match requests which do not match this predicate.
+    fn not(self) -> Not<Self>
+        where Self: Sized
+    {
+        Not(self)
+    }
+}
+
+impl<F> Predicate for F
+    where F: Fn(&Request) -> bool + Send + Sync
+{
+    fn matches(&self, req: &Request) -> bool {
+        self(req)
+    }
+}
+
+/// Matches requests which match both predicates.
+pub struct And<A, B>(pub A, pub B);
+
+impl<A, B> Predicate for And<A, B>
+    where A: Predicate,
+          B: Predicate
+{
+    fn matches(&self, req: &Request) -> bool {
+        self.0.matches(req) && self.1.matches(req)
+    }
+
+    fn describe(&self) -> String {
+        format!("({} and {})", self.0.describe(), self.1.describe())
+    }
+}
+
+/// Matches requests which match either predicate.
+pub struct Or<A, B>(pub A, pub B);
+
+impl<A, B> Predicate for Or<A, B>
+    where A: Predicate,
+          B: Predicate
+{
+    fn matches(&self, req: &Request) -> bool {
+        self.0.matches(req) || self.1.matches(req)
+    }
+
+    fn describe(&self) -> String {
+        format!("({} or {})", self.0.describe(), self.1.describe())
+    }
+}
+
+/// Matches requests which do not match the predicate.
+pub struct Not<A>(pub A);
+
+impl<A> Predicate for Not<A>
+    where A: Predicate
+{
+    fn matches(&self, req: &Request) -> bool {
+        !self.0.matches(req)
+    }
+
+    fn describe(&self) -> String {
+        format!("not({})", self.0.describe())
+    }
+}
+
+/// Matches requests with the given method.
+pub struct Method(pub method::Method);
+
+impl Predicate for Method {
+    fn matches(&self, req: &Request) -> bool {
+        req.method == self.0
+    }
+
+    fn describe(&self) -> String {
+        format!("method({})", self.0)
+    }
+}
+
+/// Matches requests with a header with the given name and value.
+pub struct Header<N, V>(pub N, pub V);
+
+impl<N, V> Predicate for Header<N, V>
+    where N: AsRef<str> + Send + Sync,
+          V: AsRef<str> + Send + Sync
+{
+    fn matches(&self, req: &Request) -> bool {
+        match req.headers.get_raw(self.0.as_ref()) {
+            Some(values) => values.iter().any(|v| v == self.1.as_ref().as_bytes()),
+            None         => false
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("header({} = {})", self.0.as_ref(), self.1.as_ref())
+    }
+}
+
+/// Matches requests to the given host, e.g. `api.example.com`.
+///
+/// The pattern may start with a `*` label which matches one or more labels,
+/// e.g. `*.example.com`, and may end with a port, e.g. `localhost:3000`.
+/// IPv6 addresses must be bracketed when followed by a port, e.g. `[::1]:3000`.
+/// Hosts are compared case-insensitively. Patterns with an invalid port never match.
+pub struct Host<S>(pub S);
+
+impl<S> Predicate for Host<S>
+    where S: AsRef<str> + Send + Sync
+{
+    fn matches(&self, req: &Request) -> bool {
+        let (hostname, port) = request_host(req);
+        match_host(self.0.as_ref(), &hostname, port).is_some()
+    }
+
+    fn describe(&self) -> String {
+        format!("host({})", self.0.as_ref())
+    }
+}
+
+/// Get the hostname and port of the request from the `Host` header,
+/// or from the request URL if the header is missing.
+pub(crate) fn request_host(req: &Request) -> (String, u16) {
+    match req.headers.get::<headers::Host>() {
+        Some(host) => (host.hostname.clone(), host.port.unwrap_or_else(|| req.url.port())),
+        None       => (req.url.host().to_string(), req.url.port())
+    }
+}
+
+/// Get the hostname and port of the request from the first entry of the
+/// `X-Forwarded-Host` header, if present. Falls back to the port of the
+/// request URL if the header has no port.
+pub(crate) fn forwarded_host(req: &Request) -> Option<(String, u16)> {
+    let value = req.headers.get_raw("X-Forwarded-Host").and_then(|values| values.first())?;
+    let value = String::from_utf8_lossy(value);
+    let host = value.split(',').next().unwrap_or("").trim();
+    if host.is_empty() {
+        return None;
+    }
+    // NOTE: Ignore colons within a bracketed IPv6 address
+    match host.rfind(':') {
+        Some(idx) if !host[idx..].contains(']') => {
+            let port = host[idx + 1..].parse::<u16>().ok()?;
+            Some((host[..idx].to_string(), port))
+        },
+        _ => Some((host.to_string(), req.url.port()))
+    }
+}
+
+/// Split a host pattern into its host and port, if any. Returns an error if the port is invalid.
+pub(crate) fn split_host_pattern(pattern: &str) -> Result<(&str, Option<u16>), String> {
+    // NOTE: Ignore colons within a bracketed IPv6 address
+    let idx = match pattern.rfind(':') {
+        Some(idx) if !pattern[idx..].contains(']') => idx,
+        _ => return Ok((pattern, None))
+    };
+    // A bare IPv6 address (e.g. `::1`) has no port
+    if !pattern.starts_with('[') && pattern[..idx].contains(':') {
+        return Ok((pattern, None));
+    }
+    match pattern[idx + 1..].parse::<u16>() {
+        Ok(port) => Ok((&pattern[..idx], Some(port))),
+        Err(_)   => Err(format!("Invalid port in host pattern \"{}\"", pattern))
+    }
+}
+
+/// Match a host pattern against a hostname and port. On success, returns
+/// the labels matched by the `*` label of the pattern, if any.
+/// Patterns with an invalid port never match.
+pub(crate) fn match_host(pattern: &str, hostname: &str, port: u16) -> Option<Option<String>> {
+    let (pattern, pattern_port) = split_host_pattern(pattern).ok()?;
+
+    if pattern_port.is_some() && pattern_port != Some(port) {
+        return None;
+    }
+
+    // NOTE: IPv6 addresses may or may not be bracketed
+    let unbracket = |host: &str| host.trim_start_matches('[').trim_end_matches(']').to_ascii_lowercase();
+    let hostname = unbracket(hostname.trim_end_matches('.'));
+    let pattern = unbracket(pattern);
+
+    if pattern.starts_with("*.") {
+        let suffix = &pattern[1..];
+        if hostname.len() > suffix.len() && hostname.ends_with(suffix) {
+            return Some(Some(hostname[..hostname.len() - suffix.len()].to_string()));
+        }
+        return None;
+    }
+
+    if hostname == pattern { Some(None) } else { None }
+}
+
+/// Matches requests with a query string parameter with the given name and value.
+pub struct Query<N, V>(pub N, pub V);
+
+impl<N, V> Predicate for Query<N, V>
+    where N: AsRef<str> + Send + Sync,
+          V: AsRef<str> + Send + Sync
+{
+    fn matches(&self, req: &Request) -> bool {
+        match req.url.query() {
+            Some(query) => {
+                form_urlencoded::parse(query.as_bytes())
+                    .any(|(name, value)| name == self.0.as_ref() && value == self.1.as_ref())
+            },
+            None => false
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("query({} = {})", self.0.as_ref(), self.1.as_ref())
+    }
+}
+
+/// Matches requests with the given content type, e.g. `application/json`.
+/// Any parameters (such as `charset`) are ignored, and the subtype may be `*`.
+pub struct ContentType<S>(pub S);
+
+impl<S> Predicate for ContentType<S>
+    where S: AsRef<str> + Send + Sync
+{
+    fn matches(&self, req: &Request) -> bool {
+        let value = match req.headers.get_raw("Content-Type").and_then(|values| values.first()) {
+            Some(value) => String::from_utf8_lossy(value).to_ascii_lowercase(),
+            None        => return false
+        };
+        let essence = value.split(';').next().unwrap_or("").trim();
+        let pattern = self.0.as_ref().to_ascii_lowercase();
+        if pattern.ends_with("/*") {
+            essence.starts_with(&pattern[..pattern.len() - 1])
+        }
+        else {
+            essence == pattern
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("content_type({})", self.0.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{match_host, split_host_pattern};
+
+    #[test]
+    fn match_host_exact() {
+        assert_eq!(match_host("api.example.com", "API.example.com", 80), Some(None));
+        assert_eq!(match_host("api.example.com", "www.example.com", 80), None);
+    }
+
+    #[test]
+    fn match_host_wildcard() {
+        assert_eq!(match_host("*.example.com", "acme.example.com", 80), Some(Some("acme".to_string())));
+        assert_eq!(match_host("*.example.com", "a.b.example.com", 80), Some(Some("a.b".to_string())));
+        assert_eq!(match_host("*.example.com", "example.com", 80), None);
+    }
+
+    #[test]
+    fn match_host_port() {
+        assert_eq!(match_host("localhost:3000", "localhost", 3000), Some(None));
+        assert_eq!(match_host("localhost:3000", "localhost", 80), None);
+    }
+
+    #[test]
+    fn match_host_ipv6() {
+        assert_eq!(match_host("[::1]", "[::1]", 80), Some(None));
+        assert_eq!(match_host("[::1]", "::1", 80), Some(None));
+        assert_eq!(match_host("::1", "[::1]", 80), Some(None));
+        assert_eq!(match_host("[::1]:3000", "[::1]", 3000), Some(None));
+        assert_eq!(match_host("[::1]:3000", "[::1]", 80), None);
+        assert_eq!(match_host("[::1]", "[::2]", 80), None);
+    }
+
+    #[test]
+    fn match_host_invalid_port() {
+        assert!(split_host_pattern("localhost:http").is_err());
+        assert!(split_host_pattern("[::1]:99999").is_err());
+        assert_eq!(match_host("localhost:http", "localhost", 80), None);
+    }
+}
@@ -0,0 +1,81 @@
+//! Support for replacing the pipeline used by a running server.
+
+use std::sync::{Arc, RwLock};
+
+use iron::prelude::*;
+use iron::middleware::Handler;
+
+use Pipeline;
+
+/// Iron handler which delegates to a pipeline which may be replaced while
+/// the server is running. Requests which are in flight when the pipeline is
+/// replaced complete on the pipeline they started on.
+///
+/// Cloning a `SwappablePipeline` produces another handle to the same pipeline,
+/// which may be kept by the application after passing the original to Iron.
+///
+/// # Examples
+///
+/// ```rust
+/// # extern crate iron;
+/// # extern crate iron_pipeline;
+/// # fn main() {
+/// # use iron::prelude::*;
+/// # use iron_pipeline::prelude::*;
+/// fn build_pipeline(greeting: &'static str) -> Pipeline {
+///     let mut pipeline = Pipeline::new();
+///     pipeline.add(Handle(move |_| Ok(Response::with(greeting))));
+///     pipeline
+/// }
+///
+/// let swappable = SwappablePipeline::new(build_pipeline("Hello"));
+/// let reloader = swappable.clone();
+///
+/// Iron::new(swappable); // etc...
+///
+/// // Later, e.g. when the configuration changes:
+/// reloader.swap(build_pipeline("Bonjour"));
+/// # }
+/// ```
+pub struct SwappablePipeline<H = Pipeline> {
+    current: Arc<RwLock<Arc<H>>>
+}
+
+impl<H> SwappablePipeline<H>
+    where H: Handler
+{
+    /// Construct a new swappable pipeline, initially delegating to `handler`.
+    pub fn new(handler: H) -> SwappablePipeline<H> {
+        SwappablePipeline { current: Arc::new(RwLock::new(Arc::new(handler))) }
+    }
+
+    /// Replace the current pipeline. Returns the previous pipeline, which
+    /// is dropped once any in-flight requests have completed.
+    pub fn swap(&self, handler: H) -> Arc<H> {
+        let mut current = self.current.write().unwrap_or_else(|err| err.into_inner());
+        ::std::mem::replace(&mut *current, Arc::new(handler))
+    }
+
+    /// Get the current pipeline.
+    pub fn current(&self) -> Arc<H> {
+        let current = self.current.read().unwrap_or_else(|err| err.into_inner());
+        current.clone()
+    }
+}
+
+impl<H> Clone for SwappablePipeline<H> {
+    fn clone(&self) -> SwappablePipeline<H> {
+        SwappablePipeline { current: self.current.clone() }
+    }
+}
+
+impl<H> Handler for SwappablePipeline<H>
+    where H: Handler
+{
+    /// Invokes the current pipeline
+    fn handle(&self, req: &mut Request) -> IronResult<Response> {
+        // NOTE: Release the lock before handling the request
+        let current = self.current();
+        current.handle(req)
+    }
+}
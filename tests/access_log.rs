@@ -0,0 +1,188 @@
+extern crate iron;
+extern crate iron_pipeline;
+extern crate iron_test;
+
+use iron::prelude::*;
+use iron::{ Headers, status };
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{ self, Write };
+use std::sync::{ Arc, Mutex };
+
+use iron_pipeline::prelude::*;
+use iron_pipeline::middleware::access_log::{ AccessLog, LogFormat, RotatingFile };
+use iron_pipeline::middleware::request_id::AssignRequestId;
+
+/// A log sink which may be inspected after the pipeline has run
+#[derive(Clone, Default)]
+struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    fn lines(&self) -> Vec<String> {
+        let buffer = self.0.lock().unwrap();
+        String::from_utf8_lossy(&buffer).lines().map(|line| line.to_string()).collect()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct Teapot;
+
+impl fmt::Display for Teapot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("I'm a teapot")
+    }
+}
+
+impl Error for Teapot {}
+
+fn build_pipeline(format: LogFormat, buffer: &SharedBuffer) -> Pipeline {
+    let mut pipeline = Pipeline::new();
+    pipeline.add(AccessLog::new(format, buffer.clone()));
+    pipeline.add(Fork::when_path("/teapot", |teapot| {
+        teapot.add(Handle(|_| Err(IronError::new(Teapot, status::ImATeapot))));
+    }));
+    pipeline.add(Handle(|_| Ok(Response::with((status::Ok, "Hello, world")))));
+    pipeline
+}
+
+fn headers() -> Headers {
+    let mut headers = Headers::new();
+    headers.set_raw("User-Agent", vec![b"curl/7.64.1".to_vec()]);
+    headers.set_raw("Referer", vec![b"http://example.com/".to_vec()]);
+    headers.set_raw("X-Request-Id", vec![b"abc-123".to_vec()]);
+    headers
+}
+
+#[test]
+fn test_access_log_common_format() {
+    let buffer = SharedBuffer::default();
+    let pipeline = build_pipeline(LogFormat::Common, &buffer);
+
+    iron_test::request::get("http://localhost:3000/hello?name=world", headers(), &pipeline).unwrap();
+
+    let lines = buffer.lines();
+    assert_eq!(lines.len(), 1);
+    assert!(lines[0].starts_with("127.0.0.1 - - ["));
+    assert!(lines[0].ends_with("] \"GET /hello?name=world HTTP/1.1\" 200 12"));
+}
+
+#[test]
+fn test_access_log_combined_format() {
+    let buffer = SharedBuffer::default();
+    let pipeline = build_pipeline(LogFormat::Combined, &buffer);
+
+    iron_test::request::get("http://localhost:3000/hello", headers(), &pipeline).unwrap();
+
+    let lines = buffer.lines();
+    assert!(lines[0].ends_with("\"GET /hello HTTP/1.1\" 200 12 \"http://example.com/\" \"curl/7.64.1\""));
+}
+
+#[test]
+fn test_access_log_json_format() {
+    let buffer = SharedBuffer::default();
+    let pipeline = build_pipeline(LogFormat::Json, &buffer);
+
+    iron_test::request::get("http://localhost:3000/hello", Headers::new(), &pipeline).unwrap();
+
+    let lines = buffer.lines();
+    assert!(lines[0].starts_with("{\"time\":\""));
+    assert!(lines[0].contains(",\"remote_addr\":\"127.0.0.1:3000\",\"method\":\"GET\",\"path\":\"/hello\",\"protocol\":\"HTTP/1.1\",\"status\":200,\"bytes\":12,\"duration_ms\":"));
+    assert!(lines[0].ends_with(",\"user_agent\":\"iron-test\",\"referer\":null,\"request_id\":null}"));
+}
+
+#[test]
+fn test_access_log_template_format() {
+    let buffer = SharedBuffer::default();
+    let template = "{request_id} {method} {path} {status} {bytes} {user_agent} {unknown}";
+    let pipeline = build_pipeline(LogFormat::Template(template.to_string()), &buffer);
+
+    iron_test::request::get("http://localhost:3000/hello", headers(), &pipeline).unwrap();
+    iron_test::request::get("http://localhost:3000/hello", Headers::new(), &pipeline).unwrap();
+
+    let lines = buffer.lines();
+    assert_eq!(lines[0], "abc-123 GET /hello 200 12 curl/7.64.1 {unknown}");
+    assert_eq!(lines[1], "- GET /hello 200 12 iron-test {unknown}");
+}
+
+#[test]
+fn test_access_log_template_values_not_substituted() {
+    let buffer = SharedBuffer::default();
+    let pipeline = build_pipeline(LogFormat::Template("{user_agent} {method}".to_string()), &buffer);
+
+    let mut headers = Headers::new();
+    headers.set_raw("User-Agent", vec![b"{method}".to_vec()]);
+    iron_test::request::get("http://localhost:3000/hello", headers, &pipeline).unwrap();
+
+    assert_eq!(buffer.lines()[0], "{method} GET");
+}
+
+#[test]
+fn test_access_log_errors() {
+    let buffer = SharedBuffer::default();
+    let pipeline = build_pipeline(LogFormat::Template("{method} {path} {status}".to_string()), &buffer);
+
+    let result = iron_test::request::get("http://localhost:3000/teapot/brew", Headers::new(), &pipeline);
+
+    assert!(result.is_err());
+    assert_eq!(buffer.lines()[0], "GET /teapot/brew 418");
+}
+
+#[test]
+fn test_access_log_rotating_file() {
+    let dir = std::env::temp_dir().join(format!("iron-pipeline-access-log-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("access.log");
+
+    let file = RotatingFile::new(&path, 40, 2).unwrap();
+    let mut pipeline = Pipeline::new();
+    pipeline.add(AccessLog::new(LogFormat::Template("{method} {path} {status}".to_string()), file));
+    pipeline.add(Handle(|_| Ok(Response::with(status::Ok))));
+
+    for path in &["/one", "/two", "/three", "/four", "/five", "/six", "/seven"] {
+        iron_test::request::get(&format!("http://localhost:3000{}", path), Headers::new(), &pipeline).unwrap();
+    }
+
+    let read = |name: &str| fs::read_to_string(dir.join(name)).unwrap();
+    assert_eq!(read("access.log"), "GET /seven 200\n");
+    assert_eq!(read("access.log.1"), "GET /five 200\nGET /six 200\n");
+    assert_eq!(read("access.log.2"), "GET /three 200\nGET /four 200\n");
+    assert!(!dir.join("access.log.3").exists());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_access_log_describe() {
+    let buffer = SharedBuffer::default();
+    let pipeline = build_pipeline(LogFormat::Combined, &buffer);
+
+    let description = pipeline.describe();
+    assert_eq!(description.middlewares[0].kind, "AccessLog");
+    assert_eq!(description.middlewares[0].detail.as_deref(), Some("combined"));
+}
+
+#[test]
+fn test_access_log_request_id() {
+    let buffer = SharedBuffer::default();
+    let mut pipeline = Pipeline::new();
+    pipeline.add(AccessLog::new(LogFormat::Template("{request_id} {status}".to_string()), buffer.clone()));
+    pipeline.add(AssignRequestId::new().generator(|_| "generated".to_string()));
+    pipeline.add(Handle(|_| Ok(Response::with(status::Ok))));
+
+    iron_test::request::get("http://localhost:3000/", Headers::new(), &pipeline).unwrap();
+
+    assert_eq!(buffer.lines()[0], "generated 200");
+}
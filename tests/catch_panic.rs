@@ -0,0 +1,62 @@
+extern crate iron;
+extern crate iron_pipeline;
+extern crate iron_test;
+
+use iron::prelude::*;
+use iron::{ Headers, status };
+
+use iron_pipeline::prelude::*;
+use iron_pipeline::middleware::catch_panic::{ Panicked, CaughtPanic };
+
+#[test]
+fn test_catch_panic() {
+    let mut pipeline = Pipeline::new();
+    pipeline.add(CatchPanic::new());
+    pipeline.add(Fork::when_path("/api", |api| {
+        api.add(Handle(|_| panic!("Oops")));
+    }));
+    pipeline.add(Handle(|_| Ok(Response::with(status::Ok))));
+
+    let response = iron_test::request::get("http://localhost/", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Ok));
+
+    let err = iron_test::request::get("http://localhost/api/users", Headers::new(), &pipeline).unwrap_err();
+    assert_eq!(err.response.status, Some(status::InternalServerError));
+    let panicked = err.error.downcast_ref::<Panicked>().unwrap();
+    assert_eq!(panicked.message, Some("Oops".to_string()));
+    assert_eq!(panicked.request, "GET http://localhost/api/users");
+    assert_eq!(panicked.to_string(), "Panicked handling GET http://localhost/api/users: Oops");
+}
+
+#[test]
+fn test_catch_panic_custom_response() {
+    let mut pipeline = Pipeline::new();
+
+    // Render the panic from an outer middleware
+    pipeline.add(HandleNext(|req, next| {
+        match next.process(req) {
+            Err(ref err) if req.extensions.get::<CaughtPanic>().is_some() => {
+                Ok(Response::with((status::ServiceUnavailable, err.error.to_string())))
+            },
+            result => result
+        }
+    }));
+    pipeline.add(CatchPanic::new().response(|_, panicked| {
+        Response::with((status::InternalServerError, panicked.message.clone().unwrap_or_default()))
+    }));
+    pipeline.add(Handle(|req| panic!("Failed on {}", req.url.path().join("/"))));
+
+    let response = iron_test::request::get("http://localhost/users/1", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::ServiceUnavailable));
+    let body = iron_test::response::extract_body_to_string(response);
+    assert_eq!(body, "Panicked handling GET http://localhost/users/1: Failed on users/1");
+
+    let mut pipeline = Pipeline::new();
+    pipeline.add(CatchPanic::new().response(|_, panicked| {
+        Response::with((status::InternalServerError, panicked.message.clone().unwrap_or_default()))
+    }));
+    pipeline.add(Handle(|_| panic!("Custom")));
+
+    let err = iron_test::request::get("http://localhost/", Headers::new(), &pipeline).unwrap_err();
+    assert_eq!(iron_test::response::extract_body_to_string(err.response), "Custom");
+}
@@ -0,0 +1,109 @@
+extern crate iron;
+extern crate iron_pipeline;
+extern crate iron_test;
+
+use iron::prelude::*;
+use iron::{ Headers, status };
+
+use iron_pipeline::prelude::*;
+use iron_pipeline::compiled::{ Diagnostic, DiagnosticKind, Severity };
+
+#[test]
+fn test_build() {
+
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Fork::when_path("/api", |api| {
+        api.add(Handle(|_| Ok(Response::with(status::Ok))));
+    }));
+    pipeline.add(Handle(|_| Ok(Response::with(status::NotFound))));
+
+    let compiled = pipeline.build().unwrap();
+    assert!(compiled.warnings().is_empty());
+
+    let response = iron_test::request::get("http://localhost/api", Headers::new(), &compiled).unwrap();
+    assert_eq!(response.status, Some(status::Ok));
+}
+
+#[test]
+fn test_build_detects_unreachable_middleware() {
+
+    let mut pipeline = Pipeline::new();
+    pipeline.add_named("app", Handle(|_| Ok(Response::with(status::Ok))));
+    pipeline.add_named("log", HandleNext(|req, next| next.process(req)));
+
+    let error = pipeline.build().err().unwrap();
+    assert_eq!(error.diagnostics, vec![
+        Diagnostic {
+            location: "#1 \"log\"".to_string(),
+            kind: DiagnosticKind::Unreachable { after: "#0 \"app\"".to_string() }
+        }
+    ]);
+}
+
+#[test]
+fn test_build_detects_shadowed_paths() {
+
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Fork::when_path("/api", |api| {
+        api.add(Handle(|_| Ok(Response::with(status::Ok))));
+    }));
+    pipeline.add(Fork::when_path("/api/v2", |v2| {
+        v2.add(Handle(|_| Ok(Response::with(status::Ok))));
+    }));
+
+    let error = pipeline.build().err().unwrap();
+    assert_eq!(error.diagnostics, vec![
+        Diagnostic {
+            location: "#1".to_string(),
+            kind: DiagnosticKind::ShadowedPath { path: "/api/v2".to_string(), by: "#0".to_string() }
+        }
+    ]);
+
+    // Paths are not shadowed by a fork which falls through
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Fork::when_path("/api", |api| {
+        api.add(Fork::when_path("/v1", |v1| {
+            v1.add(Handle(|_| Ok(Response::with(status::Ok))));
+        }));
+    }).fallthrough());
+    pipeline.add(Fork::when_path("/api/v2", |v2| {
+        v2.add(Handle(|_| Ok(Response::with(status::Ok))));
+    }));
+
+    assert!(pipeline.build().is_ok());
+}
+
+#[test]
+fn test_build_warns_on_empty_branch() {
+
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Fork::when_path("/api", |_| {}));
+
+    let compiled = pipeline.build().unwrap();
+    assert_eq!(compiled.warnings().len(), 1);
+    assert_eq!(compiled.warnings()[0].severity(), Severity::Warning);
+    assert_eq!(compiled.warnings()[0].kind, DiagnosticKind::EmptyBranch);
+    assert_eq!(compiled.warnings()[0].to_string(), "warning: #0: nested pipeline is empty");
+}
+
+#[test]
+fn test_build_detects_shadowed_path_params() {
+
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Fork::when_path("/tenants/:tenant_id", |tenant| {
+        tenant.add(Handle(|_| Ok(Response::with(status::Ok))));
+    }));
+    pipeline.add(Fork::when_path("/tenants/acme/api", |api| {
+        api.add(Handle(|_| Ok(Response::with(status::Ok))));
+    }));
+    pipeline.add(Fork::when_path("/files/*rest", |files| {
+        files.add(Handle(|_| Ok(Response::with(status::Ok))));
+    }));
+    pipeline.add(Fork::when_path("/files", |files| {
+        files.add(Handle(|_| Ok(Response::with(status::Ok))));
+    }));
+
+    let error = pipeline.build().err().unwrap();
+    let locations: Vec<_> = error.diagnostics.iter().map(|d| &d.location[..]).collect();
+    assert_eq!(locations, vec!["#1", "#3"]);
+}
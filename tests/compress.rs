@@ -0,0 +1,124 @@
+extern crate flate2;
+extern crate iron;
+extern crate iron_pipeline;
+extern crate iron_test;
+
+use iron::prelude::*;
+use iron::{ Headers, status };
+use iron::headers::ContentLength;
+
+use std::io::Read;
+
+use flate2::read::{ GzDecoder, ZlibDecoder };
+
+use iron_pipeline::prelude::*;
+use iron_pipeline::middleware::compress::Compress;
+
+fn large_body() -> String {
+    "{\"hello\":\"iron-pipeline\"}".repeat(100)
+}
+
+fn build_pipeline() -> Pipeline {
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Compress::new().exclude("application/pdf"));
+    pipeline.add(Fork::when_path("/small", |small| {
+        small.add(Handle(|_| Ok(Response::with((status::Ok, "small")))));
+    }));
+    pipeline.add(Fork::when_path("/pdf", |pdf| {
+        pdf.add(Handle(|_| {
+            let mut response = Response::with((status::Ok, large_body()));
+            response.headers.set_raw("Content-Type", vec![b"application/pdf".to_vec()]);
+            Ok(response)
+        }));
+    }));
+    pipeline.add(Fork::when_path("/encoded", |encoded| {
+        encoded.add(Handle(|_| {
+            let mut response = Response::with((status::Ok, large_body()));
+            response.headers.set_raw("Content-Encoding", vec![b"identity".to_vec()]);
+            Ok(response)
+        }));
+    }));
+    pipeline.add(Handle(|_| Ok(Response::with((status::Ok, large_body())))));
+    pipeline
+}
+
+fn get(path: &str, accept_encoding: Option<&str>, pipeline: &Pipeline) -> (Option<String>, Response) {
+    let mut headers = Headers::new();
+    if let Some(accept_encoding) = accept_encoding {
+        headers.set_raw("Accept-Encoding", vec![accept_encoding.as_bytes().to_vec()]);
+    }
+    let response = iron_test::request::get(path, headers, pipeline).unwrap();
+    let encoding = response.headers.get_raw("Content-Encoding").map(|v| String::from_utf8(v[0].clone()).unwrap());
+    (encoding, response)
+}
+
+#[test]
+fn test_compress_gzip() {
+    let pipeline = build_pipeline();
+
+    let (encoding, response) = get("http://localhost/", Some("deflate;q=0.5, gzip"), &pipeline);
+    assert_eq!(encoding, Some("gzip".to_string()));
+    assert_eq!(response.headers.get_raw("Vary").unwrap()[0], b"Accept-Encoding".to_vec());
+    let length = response.headers.get::<ContentLength>().unwrap().0;
+
+    let body = iron_test::response::extract_body_to_bytes(response);
+    assert_eq!(body.len() as u64, length);
+    assert!(body.len() < large_body().len());
+
+    let mut decoded = String::new();
+    GzDecoder::new(&body[..]).read_to_string(&mut decoded).unwrap();
+    assert_eq!(decoded, large_body());
+}
+
+#[test]
+fn test_compress_deflate() {
+    let pipeline = build_pipeline();
+
+    let (encoding, response) = get("http://localhost/", Some("gzip;q=0, br;q=0, *"), &pipeline);
+    assert_eq!(encoding, Some("deflate".to_string()));
+
+    let body = iron_test::response::extract_body_to_bytes(response);
+    let mut decoded = String::new();
+    ZlibDecoder::new(&body[..]).read_to_string(&mut decoded).unwrap();
+    assert_eq!(decoded, large_body());
+}
+
+#[cfg(feature = "brotli")]
+#[test]
+fn test_compress_brotli() {
+    extern crate brotli;
+    let pipeline = build_pipeline();
+
+    let (encoding, response) = get("http://localhost/", Some("gzip, br"), &pipeline);
+    assert_eq!(encoding, Some("br".to_string()));
+
+    let body = iron_test::response::extract_body_to_bytes(response);
+    let mut decoded = String::new();
+    brotli::Decompressor::new(&body[..], 4096).read_to_string(&mut decoded).unwrap();
+    assert_eq!(decoded, large_body());
+}
+
+#[test]
+fn test_compress_skipped() {
+    let pipeline = build_pipeline();
+
+    // No acceptable encoding
+    let (encoding, response) = get("http://localhost/", None, &pipeline);
+    assert_eq!(encoding, None);
+    assert_eq!(iron_test::response::extract_body_to_string(response), large_body());
+    let (encoding, _) = get("http://localhost/", Some("identity, gzip;q=0"), &pipeline);
+    assert_eq!(encoding, None);
+
+    // Too small
+    let (encoding, response) = get("http://localhost/small", Some("gzip"), &pipeline);
+    assert_eq!(encoding, None);
+    assert_eq!(iron_test::response::extract_body_to_string(response), "small");
+
+    // Excluded content type
+    let (encoding, _) = get("http://localhost/pdf", Some("gzip"), &pipeline);
+    assert_eq!(encoding, None);
+
+    // Already encoded
+    let (encoding, _) = get("http://localhost/encoded", Some("gzip"), &pipeline);
+    assert_eq!(encoding, Some("identity".to_string()));
+}
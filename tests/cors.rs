@@ -0,0 +1,133 @@
+extern crate iron;
+extern crate iron_pipeline;
+extern crate iron_test;
+
+use iron::prelude::*;
+use iron::{ Headers, status };
+use iron::method::Method;
+
+use iron_pipeline::prelude::*;
+use iron_pipeline::middleware::cors::Cors;
+
+fn build_pipeline() -> Pipeline {
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Fork::when_path("/api", |api| {
+        api.add(Cors::new()
+            .allow_origin("https://app.example.com")
+            .allow_origin("https://*.preview.example.com")
+            .allow_methods(vec![Method::Get, Method::Delete])
+            .allow_headers(vec!["Authorization", "Content-Type"])
+            .expose_headers(vec!["X-Request-Id"])
+            .allow_credentials()
+            .max_age(600));
+        let mut router = Router::new();
+        router.get("/users", Handle(|_| Ok(Response::with((status::Ok, "users")))));
+        router.delete("/users", Handle(|_| Ok(Response::with(status::NoContent))));
+        api.add(router);
+    }));
+    pipeline.add(Fork::when_path("/public", |public| {
+        public.add(Cors::new().allow_any_origin());
+        public.add(Handle(|_| Ok(Response::with((status::Ok, "public")))));
+    }));
+    pipeline
+}
+
+fn headers(values: &[(&'static str, &str)]) -> Headers {
+    let mut headers = Headers::new();
+    for &(name, value) in values {
+        headers.set_raw(name, vec![value.as_bytes().to_vec()]);
+    }
+    headers
+}
+
+fn header(response: &Response, name: &str) -> Option<String> {
+    response.headers.get_raw(name).map(|values| String::from_utf8(values[0].clone()).unwrap())
+}
+
+#[test]
+fn test_cors_preflight() {
+    let pipeline = build_pipeline();
+
+    let response = iron_test::request::options("http://localhost/api/users", headers(&[
+        ("Origin", "https://app.example.com"),
+        ("Access-Control-Request-Method", "DELETE"),
+        ("Access-Control-Request-Headers", "authorization, content-type")
+    ]), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NoContent));
+    assert_eq!(header(&response, "Access-Control-Allow-Origin"), Some("https://app.example.com".to_string()));
+    assert_eq!(header(&response, "Access-Control-Allow-Methods"), Some("GET, DELETE".to_string()));
+    assert_eq!(header(&response, "Access-Control-Allow-Headers"), Some("authorization, content-type".to_string()));
+    assert_eq!(header(&response, "Access-Control-Allow-Credentials"), Some("true".to_string()));
+    assert_eq!(header(&response, "Access-Control-Max-Age"), Some("600".to_string()));
+
+    // Disallowed method, header and origin
+    let response = iron_test::request::options("http://localhost/api/users", headers(&[
+        ("Origin", "https://app.example.com"),
+        ("Access-Control-Request-Method", "PUT")
+    ]), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Forbidden));
+    let response = iron_test::request::options("http://localhost/api/users", headers(&[
+        ("Origin", "https://app.example.com"),
+        ("Access-Control-Request-Method", "GET"),
+        ("Access-Control-Request-Headers", "X-Secret")
+    ]), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Forbidden));
+    let response = iron_test::request::options("http://localhost/api/users", headers(&[
+        ("Origin", "https://evil.com"),
+        ("Access-Control-Request-Method", "GET")
+    ]), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Forbidden));
+
+    // OPTIONS requests which are not preflight requests are passed on
+    let response = iron_test::request::options("http://localhost/api/users", headers(&[
+        ("Origin", "https://app.example.com")
+    ]), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Ok));
+    assert!(response.headers.get_raw("Allow").is_some());
+}
+
+#[test]
+fn test_cors_actual_request() {
+    let pipeline = build_pipeline();
+
+    let response = iron_test::request::get("http://localhost/api/users", headers(&[
+        ("Origin", "https://pr-42.preview.example.com")
+    ]), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Ok));
+    assert_eq!(header(&response, "Access-Control-Allow-Origin"), Some("https://pr-42.preview.example.com".to_string()));
+    assert_eq!(header(&response, "Access-Control-Expose-Headers"), Some("X-Request-Id".to_string()));
+    assert_eq!(header(&response, "Vary"), Some("Origin".to_string()));
+
+    // Disallowed origins receive no CORS headers, but the response still varies by origin
+    let response = iron_test::request::get("http://localhost/api/users", headers(&[
+        ("Origin", "https://evil.com")
+    ]), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Ok));
+    assert!(response.headers.get_raw("Access-Control-Allow-Origin").is_none());
+    assert_eq!(header(&response, "Vary"), Some("Origin".to_string()));
+
+    // Requests without an origin receive no CORS headers, as above
+    let response = iron_test::request::get("http://localhost/api/users", Headers::new(), &pipeline).unwrap();
+    assert!(response.headers.get_raw("Access-Control-Allow-Origin").is_none());
+    assert_eq!(header(&response, "Vary"), Some("Origin".to_string()));
+}
+
+#[test]
+fn test_cors_per_fork() {
+    let pipeline = build_pipeline();
+
+    let response = iron_test::request::get("http://localhost/public", headers(&[
+        ("Origin", "https://evil.com")
+    ]), &pipeline).unwrap();
+    assert_eq!(header(&response, "Access-Control-Allow-Origin"), Some("*".to_string()));
+    assert!(response.headers.get_raw("Access-Control-Allow-Credentials").is_none());
+
+    // Responses which allow any origin do not vary by origin
+    assert!(response.headers.get_raw("Vary").is_none());
+
+    let response = iron_test::request::options("http://localhost/public", headers(&[
+        ("Origin", "https://evil.com"),
+        ("Access-Control-Request-Method", "DELETE")
+    ]), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Forbidden));
+}
@@ -0,0 +1,137 @@
+extern crate flate2;
+extern crate iron;
+extern crate iron_pipeline;
+extern crate iron_test;
+
+use iron::prelude::*;
+use iron::{ Headers, status };
+
+use std::io::{ Read, Write };
+use std::net::TcpStream;
+
+use flate2::Compression;
+use flate2::write::{ GzEncoder, ZlibEncoder };
+
+use iron_pipeline::prelude::*;
+use iron_pipeline::middleware::decompress::{ Decompress, DecompressError, body_reader };
+
+fn build_pipeline() -> Pipeline {
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Decompress::new());
+    pipeline.add(Handle(|req| {
+        let mut body = String::new();
+        body_reader(req).read_to_string(&mut body).unwrap();
+        Ok(Response::with((status::Ok, body)))
+    }));
+    pipeline
+}
+
+/// Serve the pipeline and POST a (binary) body to it, returning the status line and response body.
+///
+/// NOTE: `iron_test` only supports string bodies, so the request is sent over a real connection
+fn post_bytes(pipeline: Pipeline, encoding: &str, body: &[u8]) -> (String, String) {
+    let mut listening = Iron::new(pipeline).http("127.0.0.1:0").unwrap();
+
+    let mut stream = TcpStream::connect(listening.socket).unwrap();
+    let head = format!("POST / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Encoding: {}\r\nContent-Length: {}\r\n\r\n", encoding, body.len());
+    stream.write_all(head.as_bytes()).unwrap();
+    stream.write_all(body).unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    listening.close().unwrap();
+
+    let (head, body) = response.split_at(response.find("\r\n\r\n").unwrap());
+    (head.lines().next().unwrap().to_string(), body[4..].to_string())
+}
+
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn zlib(data: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+fn encoded(encoding: &str) -> Headers {
+    let mut headers = Headers::new();
+    headers.set_raw("Content-Encoding", vec![encoding.as_bytes().to_vec()]);
+    headers
+}
+
+#[test]
+fn test_decompress_passthrough() {
+    let pipeline = build_pipeline();
+
+    let response = iron_test::request::post("http://localhost/", Headers::new(), "plain body", &pipeline).unwrap();
+    assert_eq!(iron_test::response::extract_body_to_string(response), "plain body");
+
+    let response = iron_test::request::post("http://localhost/", encoded("identity"), "identity body", &pipeline).unwrap();
+    assert_eq!(iron_test::response::extract_body_to_string(response), "identity body");
+}
+
+#[test]
+fn test_decompress_unsupported_encoding() {
+    let pipeline = build_pipeline();
+
+    let err = iron_test::request::post("http://localhost/", encoded("compress"), "body", &pipeline).unwrap_err();
+    assert_eq!(err.response.status, Some(status::UnsupportedMediaType));
+    let accept_encoding = String::from_utf8(err.response.headers.get_raw("Accept-Encoding").unwrap()[0].clone()).unwrap();
+    assert!(accept_encoding.contains("gzip, deflate"));
+    match err.error.downcast_ref::<DecompressError>() {
+        Some(DecompressError::UnsupportedEncoding(encoding)) => assert_eq!(encoding, "compress"),
+        other                                                => panic!("unexpected error {:?}", other)
+    }
+}
+
+#[test]
+fn test_decompress_invalid_body() {
+    let pipeline = build_pipeline();
+
+    let err = iron_test::request::post("http://localhost/", encoded("gzip"), "not really gzip", &pipeline).unwrap_err();
+    assert_eq!(err.response.status, Some(status::BadRequest));
+    assert!(err.error.to_string().starts_with("Request body could not be decoded"));
+}
+
+#[test]
+fn test_decompress_gzip_and_deflate() {
+    let (status_line, body) = post_bytes(build_pipeline(), "gzip", &gzip(b"hello gzip"));
+    assert_eq!(status_line, "HTTP/1.1 200 OK");
+    assert_eq!(body, "hello gzip");
+
+    let (status_line, body) = post_bytes(build_pipeline(), "deflate", &zlib(b"hello deflate"));
+    assert_eq!(status_line, "HTTP/1.1 200 OK");
+    assert_eq!(body, "hello deflate");
+}
+
+#[test]
+fn test_decompress_headers_updated() {
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Decompress::new());
+    pipeline.add(Decompress::new());
+    pipeline.add(Handle(|req| {
+        let encoding = req.headers.get_raw("Content-Encoding").is_some();
+        let length = req.headers.get::<iron::headers::ContentLength>().unwrap().0;
+        let mut body = String::new();
+        body_reader(req).read_to_string(&mut body).unwrap();
+        Ok(Response::with((status::Ok, format!("{} {} {}", encoding, length, body))))
+    }));
+
+    let (status_line, body) = post_bytes(pipeline, "gzip", &gzip(b"hello gzip"));
+    assert_eq!(status_line, "HTTP/1.1 200 OK");
+    assert_eq!(body, "false 10 hello gzip");
+}
+
+#[test]
+fn test_decompress_too_large() {
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Decompress::new().max_size(1024));
+    pipeline.add(Handle(|_| Ok(Response::with(status::Ok))));
+
+    let (status_line, _) = post_bytes(pipeline, "gzip", &gzip(&[b'a'; 4096]));
+    assert_eq!(status_line, "HTTP/1.1 413 Payload Too Large");
+}
@@ -0,0 +1,82 @@
+extern crate iron;
+extern crate iron_pipeline;
+
+use iron::prelude::*;
+use iron::status;
+
+use iron_pipeline::prelude::*;
+
+struct ApiHandler;
+
+impl iron::middleware::Handler for ApiHandler {
+    fn handle(&self, _: &mut Request) -> IronResult<Response> {
+        Ok(Response::with(status::Ok))
+    }
+}
+
+fn build_pipeline() -> Pipeline {
+    let mut pipeline = Pipeline::new();
+    pipeline.add_named("log", HandleNext(|req, next| next.process(req)));
+    pipeline.add(Fork::when_path("/api/v2", |v2| {
+        v2.add_named("api", ApiHandler);
+    }).fallthrough());
+    pipeline.set_fallback(Fallback::Status(status::NotFound));
+    pipeline
+}
+
+#[test]
+fn test_describe() {
+
+    let description = build_pipeline().describe();
+    assert_eq!(description.middlewares.len(), 2);
+    assert_eq!(description.fallback, "status 404 Not Found");
+
+    let log = &description.middlewares[0];
+    assert_eq!(log.name, Some("log".to_string()));
+    assert_eq!(log.kind, "HandleNext<{{closure}}>");
+
+    let fork = &description.middlewares[1];
+    assert_eq!(fork.name, None);
+    assert_eq!(fork.kind, "Fork");
+    assert_eq!(fork.detail, Some("when_path(/api/v2) fallthrough".to_string()));
+    assert_eq!(fork.path, Some("/api/v2".to_string()));
+    assert!(fork.fallthrough);
+    assert_eq!(fork.branches.len(), 1);
+
+    let api = &fork.branches[0].pipeline.middlewares[0];
+    assert_eq!(api.name, Some("api".to_string()));
+    assert_eq!(api.kind, "ApiHandler");
+    assert!(api.terminal);
+}
+
+#[test]
+fn test_describe_as_text() {
+
+    let text = build_pipeline().describe().to_string();
+    assert_eq!(text, "\
+log: HandleNext<{{closure}}>
+Fork when_path(/api/v2) fallthrough
+    api: ApiHandler
+    (fallback) raise 500 Internal Server Error
+(fallback) status 404 Not Found
+");
+}
+
+#[test]
+fn test_describe_as_json() {
+
+    let json = build_pipeline().describe().to_json();
+    assert_eq!(json, concat!(
+        r#"{"middlewares":["#,
+            r#"{"name":"log","kind":"HandleNext<{{closure}}>","detail":null,"path":null,"#,
+                r#""terminal":false,"fallthrough":false,"branches":[]},"#,
+            r#"{"name":null,"kind":"Fork","detail":"when_path(/api/v2) fallthrough","path":"/api/v2","#,
+                r#""terminal":false,"fallthrough":true,"branches":["#,
+                r#"{"label":null,"pipeline":{"middlewares":["#,
+                    r#"{"name":"api","kind":"ApiHandler","detail":null,"path":null,"#,
+                        r#""terminal":true,"fallthrough":false,"branches":[]}"#,
+                r#"],"fallback":"raise 500 Internal Server Error"}}"#,
+            r#"]}"#,
+        r#"],"fallback":"status 404 Not Found"}"#
+    ));
+}
@@ -0,0 +1,87 @@
+extern crate iron;
+extern crate iron_pipeline;
+extern crate iron_test;
+
+use iron::prelude::*;
+use iron::{ Headers, status };
+
+use std::error;
+use std::fmt;
+
+use iron_pipeline::prelude::*;
+use iron_pipeline::Error;
+
+#[derive(Debug)]
+struct Forbidden(&'static str);
+
+impl fmt::Display for Forbidden {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "Forbidden: {}", self.0)
+    }
+}
+
+impl error::Error for Forbidden {}
+
+fn build_pipeline() -> Pipeline {
+    let mut pipeline = Pipeline::new();
+    pipeline.add(ErrorBoundary::new()
+        .on(|err: &Forbidden, _| Response::with((status::Forbidden, format!("forbidden {}", err.0))))
+        .on_no_handler(|err, _| Response::with((status::NotFound, format!("missing {}", err))))
+        .on_status(status::Conflict, |_, req| Response::with((status::Conflict, format!("conflict {}", req.url.path().join("/"))))));
+    pipeline.add(Fork::when_path("/admin", |admin| {
+        admin.add(Handle(|_| Err(IronError::new(Forbidden("admin"), status::Forbidden))));
+    }));
+    pipeline.add(Fork::when_path("/conflict", |conflict| {
+        conflict.add(Handle(|_| Err(IronError::new(Forbidden("ignored"), status::Conflict))));
+    }));
+    pipeline.add(Fork::when_path("/ok", |ok| {
+        ok.add(Handle(|_| Ok(Response::with((status::Ok, "ok")))));
+    }));
+    pipeline
+}
+
+fn response(path: &str, pipeline: &Pipeline) -> (Option<status::Status>, String) {
+    let response = iron_test::request::get(path, Headers::new(), pipeline).unwrap();
+    (response.status, iron_test::response::extract_body_to_string(response))
+}
+
+#[test]
+fn test_error_boundary_handlers() {
+    let pipeline = build_pipeline();
+
+    assert_eq!(response("http://localhost/ok", &pipeline), (Some(status::Ok), "ok".to_string()));
+    assert_eq!(response("http://localhost/admin", &pipeline), (Some(status::Forbidden), "forbidden admin".to_string()));
+    assert_eq!(response("http://localhost/missing", &pipeline),
+               (Some(status::NotFound), "missing Pipeline error (Missing handler): GET http://localhost/missing".to_string()));
+
+    // Handlers are tried in order, so the type handler wins over the status handler
+    assert_eq!(response("http://localhost/conflict", &pipeline), (Some(status::Forbidden), "forbidden ignored".to_string()));
+}
+
+#[test]
+fn test_error_boundary_unhandled() {
+    let mut boundary = Pipeline::new();
+    boundary.add(ErrorBoundary::new().on_status(status::ImATeapot, |_, _| Response::with(status::Ok)));
+    boundary.add(Handle(|_| Err(IronError::new(Error::NoHandler {
+        method: iron::method::Get,
+        url: iron::Url::parse("http://localhost/").unwrap(),
+        forks: Vec::new()
+    }, status::InternalServerError))));
+
+    // Errors which match no handler are returned unchanged
+    let err = iron_test::request::get("http://localhost/", Headers::new(), &boundary).unwrap_err();
+    assert_eq!(err.response.status, Some(status::InternalServerError));
+
+    let mut otherwise = Pipeline::new();
+    otherwise.add(ErrorBoundary::new().otherwise(|err, _| Response::with((status::ServiceUnavailable, err.to_string()))));
+    otherwise.add(Handle(|_| Err(IronError::new(Forbidden("teapot"), status::ImATeapot))));
+    assert_eq!(response("http://localhost/teapot", &otherwise), (Some(status::ServiceUnavailable), "Forbidden: teapot".to_string()));
+}
+
+#[test]
+fn test_error_boundary_describe() {
+    let pipeline = build_pipeline();
+    let description = pipeline.describe();
+    assert_eq!(description.middlewares[0].kind, "ErrorBoundary");
+    assert_eq!(description.middlewares[0].detail, Some("on(Forbidden, NoHandler, 409 Conflict)".to_string()));
+}
@@ -88,3 +88,327 @@ fn test_fork_when_path_strips_prefix() {
     let result_body = iron_test::response::extract_body_to_bytes(response);
     assert_eq!(&result_body[..], &b"path:2:example:path"[..]);
 }
+
+#[test]
+fn test_fork_fallthrough() {
+
+    // build a pipeline which only handles GET requests under `/api`
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Fork::when_path("/api", |api| {
+        api.add(Fork::when(|req| req.method == Method::Get, |get| {
+            get.add(Handle(|_| {
+                Ok(Response::with((status::Ok, "api")))
+            }))
+        }));
+    }).fallthrough());
+    pipeline.add(Handle(|req| {
+        use iron_pipeline::middleware::fork::OriginalUrl;
+        assert!(req.extensions.get::<OriginalUrl>().is_none());
+        let body = req.url.path().join(":");
+        Ok(Response::with((status::NotFound, body)))
+    }));
+
+    // test GET is handled by the sub pipeline
+    let response = iron_test::request::get("http://localhost/api/example", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Ok));
+
+    // test all other methods fall through with the original url restored
+    let response = iron_test::request::delete("http://localhost/api/example", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NotFound));
+    let result_body = iron_test::response::extract_body_to_bytes(response);
+    assert_eq!(&result_body[..], &b"api:example"[..]);
+}
+
+#[test]
+fn test_fork_without_fallthrough_uses_sub_pipeline_fallback() {
+
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Fork::when_path("/api", |_| {}));
+    pipeline.add(Handle(|_| {
+        Ok(Response::with(status::Ok))
+    }));
+
+    let error = iron_test::request::get("http://localhost/api/example", Headers::new(), &pipeline).unwrap_err();
+    assert_eq!(error.response.status, Some(status::InternalServerError));
+}
+
+#[test]
+fn test_fork_try_when_path() {
+    use iron_pipeline::middleware::fork::PathErrorKind;
+
+    let error = Fork::try_when_path("api/v2", |_| {}).err().unwrap();
+    assert_eq!(error.kind(), PathErrorKind::NoLeadingSlash);
+    assert_eq!(error.path(), "api/v2");
+    assert_eq!(error.to_string(), "Invalid fork path \"api/v2\": Path must start with /");
+
+    let error = Fork::try_when_path("//", |_| {}).err().unwrap();
+    assert_eq!(error.kind(), PathErrorKind::PathEmpty);
+
+    assert!(Fork::try_when_path("/api/v2", |_| {}).is_ok());
+}
+
+#[test]
+fn test_fork_when_path_params() {
+    use iron_pipeline::middleware::fork::RouteParams;
+
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Fork::when_path("/tenants/:tenant_id", |tenant| {
+        tenant.add(Fork::when_path("/files/*rest", |files| {
+            files.add(Handle(|req| {
+                let params = req.extensions.get::<RouteParams>().unwrap();
+                let body = format!("{}:{}:{}", params.get("tenant_id").unwrap(), params.get("rest").unwrap(), req.url.path().join("/"));
+                Ok(Response::with((status::Ok, body)))
+            }))
+        }));
+    }));
+
+    let response = iron_test::request::get("http://localhost/tenants/acme/files/a/b.txt", Headers::new(), &pipeline).unwrap();
+    let result_body = iron_test::response::extract_body_to_bytes(response);
+    assert_eq!(&result_body[..], &b"acme:a/b.txt:a/b.txt"[..]);
+}
+
+#[test]
+fn test_fork_when_path_params_not_empty() {
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Fork::when_path("/tenants/:tenant_id", |tenant| {
+        tenant.add(Handle(|_| Ok(Response::with((status::Ok, "tenant")))));
+    }));
+    pipeline.add(Handle(|_| Ok(Response::with((status::NotFound, "not found")))));
+
+    let response = iron_test::request::get("http://localhost/tenants/", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NotFound));
+
+    let response = iron_test::request::get("http://localhost/tenants//files", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NotFound));
+
+    let response = iron_test::request::get("http://localhost/tenants/acme", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Ok));
+}
+
+#[test]
+fn test_fork_when_path_wildcard_mount_path() {
+    use iron_pipeline::middleware::fork::{ MountPath, absolute_path };
+
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Fork::when_path("/static/*path", |files| {
+        files.add(Handle(|req| {
+            let prefixes = req.extensions.get::<MountPath>().unwrap().prefixes().join(",");
+            let body = format!("{} {} /{}", prefixes, absolute_path(req, "index.html"), req.url.path().join("/"));
+            Ok(Response::with((status::Ok, body)))
+        }));
+    }));
+
+    let response = iron_test::request::get("http://localhost/static/css/site.css", Headers::new(), &pipeline).unwrap();
+    let result_body = iron_test::response::extract_body_to_bytes(response);
+    assert_eq!(&result_body[..], &b"/static /static/index.html /css/site.css"[..]);
+}
+
+#[test]
+fn test_fork_when_path_mount_path() {
+    use iron_pipeline::middleware::fork::{ MountPath, absolute_path, absolute_url };
+
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Fork::when_path("/tenants/:tenant_id", |tenant| {
+        tenant.add(Fork::when_path("/api", |api| {
+            api.add(Handle(|req| {
+                let prefixes = req.extensions.get::<MountPath>().unwrap().prefixes().join(",");
+                let body = format!("{} {} {}", prefixes, absolute_path(req, "users/1"), absolute_url(req, "/login"));
+                Ok(Response::with((status::Ok, body)))
+            }))
+        }));
+    }));
+
+    let response = iron_test::request::get("http://localhost:3000/tenants/acme/api/users?page=1", Headers::new(), &pipeline).unwrap();
+    let result_body = iron_test::response::extract_body_to_bytes(response);
+    assert_eq!(&result_body[..], &b"/tenants/acme,/api /tenants/acme/api/users/1 http://localhost:3000/tenants/acme/api/login"[..]);
+}
+
+#[test]
+fn test_fork_fallthrough_restores_mount_path() {
+    use iron_pipeline::middleware::fork::{ MountPath, RouteParams };
+
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Fork::when_path("/tenants/:tenant_id", |_| {}).fallthrough());
+    pipeline.add(Handle(|req| {
+        assert!(req.extensions.get::<MountPath>().is_none());
+        assert!(req.extensions.get::<RouteParams>().is_none());
+        Ok(Response::with(status::Ok))
+    }));
+
+    let response = iron_test::request::get("http://localhost/tenants/acme", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Ok));
+}
+
+#[test]
+fn test_fork_when_host() {
+    use iron_pipeline::middleware::fork::Subdomain;
+
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Fork::when_host("api.example.com", |api| {
+        api.add(Handle(|_| Ok(Response::with((status::Ok, "api")))));
+    }));
+    pipeline.add(Fork::when_host("*.tenant.example.com", |tenants| {
+        tenants.add(Handle(|req| {
+            let tenant = req.extensions.get::<Subdomain>().unwrap().clone();
+            Ok(Response::with((status::Ok, tenant)))
+        }));
+    }));
+    pipeline.add(Fork::when_host("localhost:3000", |local| {
+        local.add(Handle(|_| Ok(Response::with((status::Ok, "local")))));
+    }));
+    pipeline.add(Handle(|_| Ok(Response::with(status::NotFound))));
+
+    let response = iron_test::request::get("http://API.example.com/users", Headers::new(), &pipeline).unwrap();
+    let result_body = iron_test::response::extract_body_to_bytes(response);
+    assert_eq!(&result_body[..], b"api");
+
+    let response = iron_test::request::get("http://acme.tenant.example.com/", Headers::new(), &pipeline).unwrap();
+    let result_body = iron_test::response::extract_body_to_bytes(response);
+    assert_eq!(&result_body[..], b"acme");
+
+    let response = iron_test::request::get("http://localhost:3000/", Headers::new(), &pipeline).unwrap();
+    let result_body = iron_test::response::extract_body_to_bytes(response);
+    assert_eq!(&result_body[..], b"local");
+
+    let response = iron_test::request::get("http://localhost:4000/", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NotFound));
+
+    let response = iron_test::request::get("http://tenant.example.com/", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NotFound));
+}
+
+#[test]
+fn test_fork_when_host_ipv6() {
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Fork::when_host("[::1]:3000", |local| {
+        local.add(Handle(|_| Ok(Response::with((status::Ok, "local:3000")))));
+    }));
+    pipeline.add(Fork::when_host("[::1]", |local| {
+        local.add(Handle(|_| Ok(Response::with((status::Ok, "local")))));
+    }));
+    pipeline.add(Handle(|_| Ok(Response::with(status::NotFound))));
+
+    let response = iron_test::request::get("http://[::1]/", Headers::new(), &pipeline).unwrap();
+    let result_body = iron_test::response::extract_body_to_bytes(response);
+    assert_eq!(&result_body[..], b"local");
+
+    let response = iron_test::request::get("http://[::1]:3000/", Headers::new(), &pipeline).unwrap();
+    let result_body = iron_test::response::extract_body_to_bytes(response);
+    assert_eq!(&result_body[..], b"local:3000");
+
+    let response = iron_test::request::get("http://[::2]/", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NotFound));
+}
+
+#[test]
+#[should_panic(expected = "Invalid port in host pattern \"localhost:http\"")]
+fn test_fork_when_host_invalid_port() {
+    Fork::when_host("localhost:http", |_| {});
+}
+
+#[test]
+fn test_fork_when_host_forwarded() {
+
+    let mut headers = Headers::new();
+    headers.set_raw("X-Forwarded-Host", vec![b"api.example.com, proxy.internal".to_vec()]);
+
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Fork::when_host("api.example.com", |api| {
+        api.add(Handle(|_| Ok(Response::with(status::Ok))));
+    }));
+    pipeline.add(Handle(|_| Ok(Response::with(status::NotFound))));
+
+    // Not trusted by default
+    let response = iron_test::request::get("http://localhost/", headers.clone(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NotFound));
+
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Fork::when_host("api.example.com", |api| {
+        api.add(Handle(|_| Ok(Response::with(status::Ok))));
+    }).trust_forwarded_host());
+    pipeline.add(Handle(|_| Ok(Response::with(status::NotFound))));
+
+    let response = iron_test::request::get("http://localhost/", headers, &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Ok));
+
+    // Falls back to the Host header when no forwarded host is present
+    let response = iron_test::request::get("http://api.example.com/", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Ok));
+}
+
+#[test]
+fn test_fork_custom_handler() {
+    use iron_pipeline::middleware::fork::ForkHandler;
+
+    // Forks on a "lang" query parameter, moving it into the path
+    struct LangFork;
+
+    impl ForkHandler for LangFork {
+        fn should_fork(&self, req: &Request) -> bool {
+            req.url.query().is_some_and(|q| q.starts_with("lang="))
+        }
+
+        fn modify_request(&self, req: &mut Request) {
+            let lang = req.url.query().unwrap()[5..].to_string();
+            let mut url: iron::url::Url = req.url.clone().into();
+            url.set_query(None);
+            url.set_path(&lang);
+            req.url = iron::Url::from_generic_url(url).unwrap();
+        }
+
+        fn after_fork(&self, _: &mut Request, result: &mut IronResult<Response>) {
+            if let Ok(ref mut response) = *result {
+                response.headers.set_raw("X-Lang-Fork", vec![b"1".to_vec()]);
+            }
+        }
+
+        fn describe(&self) -> String {
+            "lang".to_string()
+        }
+    }
+
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Fork::new(LangFork, |lang| {
+        lang.add(Handle(|req| Ok(Response::with((status::Ok, req.url.path().join("/"))))));
+    }));
+    pipeline.add(Fork::new(LangFork, |_| {}).fallthrough());
+    pipeline.add(Handle(|_| Ok(Response::with(status::NotFound))));
+
+    let response = iron_test::request::get("http://localhost/?lang=fr", Headers::new(), &pipeline).unwrap();
+    assert!(response.headers.get_raw("X-Lang-Fork").is_some());
+    let result_body = iron_test::response::extract_body_to_bytes(response);
+    assert_eq!(&result_body[..], b"fr");
+
+    let response = iron_test::request::get("http://localhost/", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NotFound));
+    assert!(response.headers.get_raw("X-Lang-Fork").is_none());
+
+    assert_eq!(pipeline.describe().middlewares[0].detail, Some("lang".to_string()));
+}
+
+#[test]
+fn test_fork_after_fork_skipped_on_fallthrough() {
+    use iron_pipeline::middleware::fork::ForkHandler;
+
+    struct Always;
+
+    impl ForkHandler for Always {
+        fn should_fork(&self, _: &Request) -> bool {
+            true
+        }
+
+        fn after_fork(&self, _: &mut Request, _: &mut IronResult<Response>) {
+            panic!("after_fork should not be invoked");
+        }
+    }
+
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Fork::new(Always, |_| {}).fallthrough());
+    pipeline.add(Handle(|_| Ok(Response::with(status::Ok))));
+
+    // Handlers are described by their type name by default
+    assert_eq!(pipeline.describe().middlewares[0].detail.as_deref(), Some("Always fallthrough"));
+
+    let response = iron_test::request::get("http://localhost/", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Ok));
+}
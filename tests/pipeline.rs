@@ -0,0 +1,125 @@
+extern crate iron;
+extern crate iron_pipeline;
+extern crate iron_test;
+
+use iron::prelude::*;
+use iron::{ Headers, status };
+use iron::method::{ Method };
+
+use iron_pipeline::prelude::*;
+use iron_pipeline::{ Error, EditError, Selector };
+
+#[test]
+fn test_default_fallback_raises_no_handler() {
+
+    // build a pipeline which forks on `/api` but never handles the request
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Fork::when_path("/api", |_| {}));
+
+    let error = iron_test::request::get("http://localhost/api/example", Headers::new(), &pipeline).unwrap_err();
+    assert_eq!(error.response.status, Some(status::InternalServerError));
+
+    let error = error.error.downcast_ref::<Error>().unwrap();
+    match *error {
+        Error::NoHandler { ref method, ref url, ref forks } => {
+            assert_eq!(method, &Method::Get);
+            assert_eq!(url.path(), vec!["api", "example"]);
+            assert_eq!(forks, &vec!["when_path(/api)".to_string()]);
+        }
+    }
+}
+
+#[test]
+fn test_fallback_status() {
+
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Fork::when_path("/api", |api| {
+        api.set_fallback(Fallback::Status(status::NotFound));
+    }));
+    pipeline.set_fallback(Fallback::Raise(status::BadRequest));
+
+    // test the sub pipeline fallback
+    let response = iron_test::request::get("http://localhost/api/example", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NotFound));
+
+    // test the root pipeline fallback
+    let error = iron_test::request::get("http://localhost/example", Headers::new(), &pipeline).unwrap_err();
+    assert_eq!(error.response.status, Some(status::BadRequest));
+}
+
+#[test]
+fn test_fallback_middleware() {
+
+    let mut pipeline = Pipeline::new();
+    pipeline.set_fallback(Fallback::with(Handle(|_| {
+        Ok(Response::with((status::NotFound, "Not Found")))
+    })));
+
+    let response = iron_test::request::get("http://localhost/", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NotFound));
+
+    let response_body = iron_test::response::extract_body_to_bytes(response);
+    assert_eq!(response_body, b"Not Found");
+
+    // A fallback which invokes the next middleware raises `NoHandler`
+    let mut pipeline = Pipeline::new();
+    pipeline.set_fallback(Fallback::with(HandleNext(|req, next| next.process(req))));
+
+    let error = iron_test::request::get("http://localhost/", Headers::new(), &pipeline).unwrap_err();
+    assert_eq!(error.response.status, Some(status::InternalServerError));
+}
+
+fn names(pipeline: &Pipeline) -> Vec<String> {
+    pipeline.describe().middlewares.into_iter()
+        .map(|m| m.name.unwrap_or_else(|| "?".to_string()))
+        .collect()
+}
+
+fn passthrough() -> HandleNext<fn(&mut Request, iron_pipeline::PipelineNext) -> IronResult<Response>> {
+    fn process(req: &mut Request, next: iron_pipeline::PipelineNext) -> IronResult<Response> {
+        next.process(req)
+    }
+    HandleNext(process)
+}
+
+#[test]
+fn test_edit_pipeline() {
+
+    let mut pipeline = Pipeline::new();
+    pipeline.add_named("log", passthrough());
+    pipeline.add_named("app", Handle(|_| Ok(Response::with(status::Ok))));
+
+    pipeline.insert_named_before("app", "auth", passthrough()).unwrap();
+    pipeline.insert_named_after(0, "request-id", passthrough()).unwrap();
+    assert_eq!(names(&pipeline), vec!["log", "request-id", "auth", "app"]);
+
+    pipeline.remove("log").unwrap();
+    pipeline.replace("app", Handle(|_| Ok(Response::with(status::Accepted)))).unwrap();
+    assert_eq!(names(&pipeline), vec!["request-id", "auth", "app"]);
+
+    let response = iron_test::request::get("http://localhost/", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Accepted));
+
+    // test invalid selectors
+    assert_eq!(pipeline.remove("missing"), Err(EditError::NotFound(Selector::Name("missing".to_string()))));
+    assert_eq!(pipeline.insert_before(3, passthrough()), Err(EditError::NotFound(Selector::Index(3))));
+}
+
+#[test]
+fn test_edit_nested_pipeline() {
+
+    let mut pipeline = Pipeline::new();
+    pipeline.add_named("log", passthrough());
+    pipeline.add_named("api", Fork::when_path("/api", |api| {
+        api.add_named("app", Handle(|_| Ok(Response::with(status::Ok))));
+    }));
+
+    pipeline.nested_mut("api").unwrap()
+        .insert_named_before("app", "deny", Handle(|_| Ok(Response::with(status::Forbidden)))).unwrap();
+
+    let response = iron_test::request::get("http://localhost/api/example", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Forbidden));
+
+    // test middleware without a nested pipeline
+    assert!(pipeline.nested_mut("log").is_err());
+}
@@ -0,0 +1,85 @@
+extern crate iron;
+extern crate iron_pipeline;
+extern crate iron_test;
+
+use iron::prelude::*;
+use iron::{ Headers, status };
+use iron::method::{ Get, Post };
+
+use iron_pipeline::prelude::*;
+use iron_pipeline::predicate::{ Predicate, Method, Header, Host, Query, ContentType };
+
+fn fork_status<P: Predicate + 'static>(predicate: P) -> Pipeline {
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Fork::when_matches(predicate, |sub| {
+        sub.add(Handle(|_| Ok(Response::with(status::Ok))));
+    }));
+    pipeline.add(Handle(|_| Ok(Response::with(status::NotFound))));
+    pipeline
+}
+
+fn headers(name: &'static str, value: &str) -> Headers {
+    let mut headers = Headers::new();
+    headers.set_raw(name, vec![value.as_bytes().to_vec()]);
+    headers
+}
+
+#[test]
+fn test_predicates() {
+
+    let pipeline = fork_status(Method(Post));
+    let response = iron_test::request::post("http://localhost/", Headers::new(), "", &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Ok));
+    let response = iron_test::request::get("http://localhost/", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NotFound));
+
+    let pipeline = fork_status(Header("X-ApiVersion", "2009-01-01"));
+    let response = iron_test::request::get("http://localhost/", headers("X-ApiVersion", "2009-01-01"), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Ok));
+    let response = iron_test::request::get("http://localhost/", headers("X-ApiVersion", "2010-01-01"), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NotFound));
+
+    let pipeline = fork_status(Host("*.example.com"));
+    let response = iron_test::request::get("http://acme.example.com/", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Ok));
+    let response = iron_test::request::get("http://example.com/", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NotFound));
+
+    let pipeline = fork_status(Query("v", "2"));
+    let response = iron_test::request::get("http://localhost/?a=1&v=2", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Ok));
+    let response = iron_test::request::get("http://localhost/?v=1", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NotFound));
+
+    let pipeline = fork_status(ContentType("application/json"));
+    let response = iron_test::request::post("http://localhost/", headers("Content-Type", "application/JSON; charset=utf-8"), "{}", &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Ok));
+    let response = iron_test::request::post("http://localhost/", headers("Content-Type", "text/plain"), "", &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NotFound));
+}
+
+#[test]
+fn test_predicate_combinators() {
+
+    let pipeline = fork_status(Method(Get).or(Method(Post)).and(Query("debug", "1").not()));
+
+    let response = iron_test::request::post("http://localhost/", Headers::new(), "", &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Ok));
+    let response = iron_test::request::get("http://localhost/?debug=1", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NotFound));
+    let response = iron_test::request::delete("http://localhost/", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NotFound));
+
+    // closures may be combined with other predicates
+    let pipeline = fork_status((|req: &Request| req.url.path() == vec!["admin"]).and(Method(Get)));
+    let response = iron_test::request::get("http://localhost/admin", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Ok));
+}
+
+#[test]
+fn test_predicate_describe() {
+
+    let pipeline = fork_status(Method(Get).or(Method(Post)).and(Header("X-ApiVersion", "2").not()));
+    let description = pipeline.describe();
+    assert_eq!(description.middlewares[0].detail, Some("when(((method(GET) or method(POST)) and not(header(X-ApiVersion = 2))))".to_string()));
+}
@@ -0,0 +1,110 @@
+extern crate iron;
+extern crate iron_pipeline;
+extern crate iron_test;
+
+use iron::prelude::*;
+use iron::{ Headers, status };
+
+use iron_pipeline::prelude::*;
+use iron_pipeline::middleware::problem::{ Problems, ProblemDetails };
+use iron_pipeline::middleware::version::VersionStrategy;
+
+fn build_pipeline() -> Pipeline {
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Problems::new());
+    pipeline.add(CatchPanic::new());
+    pipeline.add(Versioned::new()
+        .strategy(VersionStrategy::PathSegment("v".to_string()))
+        .version("1", |v1| {
+            v1.add(Fork::when_path("/panic", |panic| {
+                panic.add(Handle(|_| panic!("Secret")));
+            }));
+            v1.add(Fork::when_path("/forbidden", |forbidden| {
+                forbidden.add(Handle(|_| Err(IronError::new(Error::new("Not your account"), status::Forbidden))));
+            }));
+        }));
+    pipeline
+}
+
+#[derive(Debug)]
+struct Error(&'static str);
+
+impl Error {
+    fn new(message: &'static str) -> Error {
+        Error(message)
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "{}", self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+fn problem(url: &str, accept: Option<&str>, pipeline: &Pipeline) -> (Option<status::Status>, String, String) {
+    let mut headers = Headers::new();
+    if let Some(accept) = accept {
+        headers.set_raw("Accept", vec![accept.as_bytes().to_vec()]);
+    }
+    let err = iron_test::request::get(url, headers, pipeline).unwrap_err();
+    let content_type = String::from_utf8(err.response.headers.get_raw("Content-Type").unwrap()[0].clone()).unwrap();
+    (err.response.status, content_type, iron_test::response::extract_body_to_string(err.response))
+}
+
+#[test]
+fn test_problem_details_json() {
+    let pipeline = build_pipeline();
+
+    let (status, content_type, body) = problem("http://localhost/v1/missing", None, &pipeline);
+    assert_eq!(status, Some(status::InternalServerError));
+    assert_eq!(content_type, "application/problem+json");
+    assert_eq!(body, r#"{"type":"about:blank","title":"Missing handler","status":500,"detail":"No handler for GET /v1/missing","instance":"/v1/missing"}"#);
+
+    let (status, _, body) = problem("http://localhost/v3/users", Some("application/json"), &pipeline);
+    assert_eq!(status, Some(status::BadRequest));
+    assert_eq!(body, r#"{"type":"about:blank","title":"Bad Request","status":400,"detail":"Unsupported API version \"3\" (supported versions: 1)","instance":"/v3/users"}"#);
+
+    let (_, _, body) = problem("http://localhost/v1/forbidden", None, &pipeline);
+    assert_eq!(body, r#"{"type":"about:blank","title":"Forbidden","status":403,"detail":"Not your account","instance":"/v1/forbidden"}"#);
+
+    // Panic messages are not leaked
+    let (status, _, body) = problem("http://localhost/v1/panic", None, &pipeline);
+    assert_eq!(status, Some(status::InternalServerError));
+    assert_eq!(body, r#"{"type":"about:blank","title":"Internal Server Error","status":500,"instance":"/v1/panic"}"#);
+}
+
+#[test]
+fn test_problem_details_text() {
+    let pipeline = build_pipeline();
+
+    let (_, content_type, body) = problem("http://localhost/v1/forbidden", Some("text/plain"), &pipeline);
+    assert_eq!(content_type, "text/plain; charset=utf-8");
+    assert_eq!(body, "403 Forbidden: Not your account\n");
+
+    let (_, content_type, _) = problem("http://localhost/v1/forbidden", Some("text/*;q=0.5, application/json"), &pipeline);
+    assert_eq!(content_type, "application/problem+json");
+
+    let (_, content_type, _) = problem("http://localhost/v1/forbidden", Some("text/html, text/plain;q=0.9, */*;q=0.1"), &pipeline);
+    assert_eq!(content_type, "text/plain; charset=utf-8");
+}
+
+#[test]
+fn test_problem_details_map_and_modifier() {
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Problems::new().map(|problem, _, _| problem.problem_type("https://example.com/problems/missing")));
+    pipeline.add(Fork::when_path("/credit", |credit| {
+        credit.add(Handle(|_| {
+            Ok(Response::with(ProblemDetails::new(status::Forbidden).detail("Out of \"credit\"")))
+        }));
+    }));
+
+    let (_, _, body) = problem("http://localhost/missing", None, &pipeline);
+    assert!(body.starts_with(r#"{"type":"https://example.com/problems/missing","#));
+
+    let response = iron_test::request::get("http://localhost/credit", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Forbidden));
+    let body = iron_test::response::extract_body_to_string(response);
+    assert_eq!(body, r#"{"type":"about:blank","title":"Forbidden","status":403,"detail":"Out of \"credit\""}"#);
+}
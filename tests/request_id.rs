@@ -0,0 +1,123 @@
+extern crate iron;
+extern crate iron_pipeline;
+extern crate iron_test;
+
+use iron::prelude::*;
+use iron::{ Headers, status };
+
+use std::fmt;
+use std::error::Error;
+
+use iron_pipeline::prelude::*;
+use iron_pipeline::middleware::problem::Problems;
+use iron_pipeline::middleware::request_id::{ AssignRequestId, RequestId };
+
+#[derive(Debug)]
+struct NotFound;
+
+impl fmt::Display for NotFound {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("No such user")
+    }
+}
+
+impl Error for NotFound {}
+
+fn build_pipeline(assign: AssignRequestId) -> Pipeline {
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Problems::new());
+    pipeline.add(assign);
+    pipeline.add(Fork::when_path("/missing", |missing| {
+        missing.add(Handle(|_| Err(IronError::new(NotFound, status::NotFound))));
+    }));
+    pipeline.add(Handle(|req| {
+        let id = req.extensions.get::<RequestId>().unwrap().clone();
+        Ok(Response::with((status::Ok, id)))
+    }));
+    pipeline
+}
+
+fn header(response: &Response, name: &str) -> Option<String> {
+    response.headers.get_raw(name).map(|values| String::from_utf8(values[0].clone()).unwrap())
+}
+
+fn get(url: &str, request_id: Option<&str>, pipeline: &Pipeline) -> (Option<String>, String) {
+    let mut headers = Headers::new();
+    if let Some(request_id) = request_id {
+        headers.set_raw("X-Request-Id", vec![request_id.as_bytes().to_vec()]);
+    }
+    let response = iron_test::request::get(url, headers, pipeline).unwrap();
+    (header(&response, "X-Request-Id"), iron_test::response::extract_body_to_string(response))
+}
+
+fn is_uuid(id: &str) -> bool {
+    let groups: Vec<&str> = id.split('-').collect();
+    groups.iter().map(|g| g.len()).collect::<Vec<_>>() == vec![8, 4, 4, 4, 12]
+        && groups[2].starts_with('4')
+        && id.chars().all(|c| c == '-' || c.is_ascii_hexdigit())
+}
+
+#[test]
+fn test_request_id_incoming() {
+    let pipeline = build_pipeline(AssignRequestId::new());
+
+    let (echoed, body) = get("http://localhost/", Some("abc-123"), &pipeline);
+    assert_eq!(echoed, Some("abc-123".to_string()));
+    assert_eq!(body, "abc-123");
+}
+
+#[test]
+fn test_request_id_generated() {
+    let pipeline = build_pipeline(AssignRequestId::new());
+
+    let (first, body) = get("http://localhost/", None, &pipeline);
+    let first = first.unwrap();
+    assert!(is_uuid(&first), "{}", first);
+    assert_eq!(body, first);
+
+    let (second, _) = get("http://localhost/", Some("not valid!"), &pipeline);
+    let second = second.unwrap();
+    assert!(is_uuid(&second), "{}", second);
+    assert_ne!(first, second);
+}
+
+#[test]
+fn test_request_id_ignore_incoming() {
+    let pipeline = build_pipeline(AssignRequestId::new().ignore_incoming());
+
+    let (echoed, _) = get("http://localhost/", Some("abc-123"), &pipeline);
+    assert!(is_uuid(&echoed.unwrap()));
+}
+
+#[test]
+fn test_request_id_custom_header_and_generator() {
+    let pipeline = build_pipeline(AssignRequestId::new()
+        .header("X-Correlation-Id")
+        .generator(|req| format!("{}-generated", req.method)));
+
+    let response = iron_test::request::get("http://localhost/", Headers::new(), &pipeline).unwrap();
+    assert_eq!(header(&response, "X-Correlation-Id"), Some("GET-generated".to_string()));
+    assert_eq!(header(&response, "X-Request-Id"), None);
+}
+
+#[test]
+fn test_request_id_on_errors() {
+    let pipeline = build_pipeline(AssignRequestId::new());
+
+    let mut headers = Headers::new();
+    headers.set_raw("X-Request-Id", vec![b"abc-123".to_vec()]);
+    let err = iron_test::request::get("http://localhost/missing", headers, &pipeline).unwrap_err();
+
+    assert_eq!(header(&err.response, "X-Request-Id"), Some("abc-123".to_string()));
+    let body = iron_test::response::extract_body_to_string(err.response);
+    assert_eq!(body, r#"{"type":"about:blank","title":"Not Found","status":404,"detail":"No such user","instance":"/missing","request_id":"abc-123"}"#);
+}
+
+#[test]
+fn test_request_id_describe() {
+    let pipeline = build_pipeline(AssignRequestId::new().ignore_incoming());
+
+    let description = pipeline.describe();
+    assert_eq!(description.middlewares[1].kind, "AssignRequestId");
+    assert_eq!(description.middlewares[1].detail.as_deref(), Some("X-Request-Id (ignore incoming)"));
+}
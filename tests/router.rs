@@ -0,0 +1,111 @@
+extern crate iron;
+extern crate iron_pipeline;
+extern crate iron_test;
+
+use iron::prelude::*;
+use iron::{ Headers, status };
+use iron::headers::Allow;
+use iron::method::Method;
+
+use iron_pipeline::prelude::*;
+use iron_pipeline::middleware::fork::RouteParams;
+
+fn echo_params(req: &mut Request) -> IronResult<Response> {
+    let params = req.extensions.get::<RouteParams>().unwrap();
+    let body: Vec<String> = params.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+    Ok(Response::with((status::Ok, body.join(","))))
+}
+
+fn build_pipeline() -> Pipeline {
+    let mut router = Router::new();
+    router.get("/users", Handle(|_| Ok(Response::with((status::Ok, "users")))));
+    router.get("/users/me", Handle(|_| Ok(Response::with((status::Ok, "me")))));
+    router.get("/users/:id", Handle(echo_params));
+    router.delete("/users/:id", Handle(|_| Ok(Response::with(status::NoContent))));
+    router.get("/files/*path", Handle(echo_params));
+
+    let mut pipeline = Pipeline::new();
+    pipeline.add(router);
+    pipeline.add(Handle(|_| Ok(Response::with(status::NotFound))));
+    pipeline
+}
+
+fn get_body(path: &str, pipeline: &Pipeline) -> Vec<u8> {
+    let response = iron_test::request::get(path, Headers::new(), pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Ok));
+    iron_test::response::extract_body_to_bytes(response)
+}
+
+#[test]
+fn test_router_dispatch() {
+
+    let pipeline = build_pipeline();
+
+    assert_eq!(get_body("http://localhost/users", &pipeline), b"users");
+    assert_eq!(get_body("http://localhost/users/", &pipeline), b"users");
+    assert_eq!(get_body("http://localhost/users/me", &pipeline), b"me");
+    assert_eq!(get_body("http://localhost/users/42", &pipeline), b"id=42");
+    assert_eq!(get_body("http://localhost/files/a/b%20c.txt", &pipeline), b"path=a/b c.txt");
+
+    let response = iron_test::request::delete("http://localhost/users/42", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NoContent));
+
+    // test unmatched paths are passed to the next middleware
+    let response = iron_test::request::get("http://localhost/users/42/posts", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NotFound));
+}
+
+#[test]
+fn test_router_empty_segments() {
+
+    let pipeline = build_pipeline();
+
+    // Only a trailing empty segment is ignored
+    for path in &["http://localhost/users//42", "http://localhost//users", "http://localhost/users//"] {
+        let response = iron_test::request::get(path, Headers::new(), &pipeline).unwrap();
+        assert_eq!(response.status, Some(status::NotFound), "{}", path);
+    }
+}
+
+#[test]
+fn test_router_method_not_allowed() {
+
+    let pipeline = build_pipeline();
+
+    let response = iron_test::request::post("http://localhost/users/42", Headers::new(), "", &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::MethodNotAllowed));
+    assert_eq!(response.headers.get::<Allow>(), Some(&Allow(vec![Method::Delete, Method::Get, Method::Head, Method::Options])));
+
+    let response = iron_test::request::options("http://localhost/users", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Ok));
+    assert_eq!(response.headers.get::<Allow>(), Some(&Allow(vec![Method::Get, Method::Head, Method::Options])));
+
+    // test HEAD is handled by the GET route
+    let response = iron_test::request::head("http://localhost/users", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Ok));
+}
+
+#[test]
+fn test_router_literal_falls_back_to_param_by_method() {
+
+    let pipeline = build_pipeline();
+
+    // "/users/me" has no DELETE route, so the "/users/:id" route handles it
+    let response = iron_test::request::delete("http://localhost/users/me", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NoContent));
+
+    // ... but the literal route is still preferred for GET requests
+    assert_eq!(&get_body("http://localhost/users/me", &pipeline)[..], b"me");
+
+    // Methods handled by neither route are not allowed by the literal route
+    let response = iron_test::request::post("http://localhost/users/me", Headers::new(), "", &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::MethodNotAllowed));
+    assert_eq!(response.headers.get::<Allow>(), Some(&Allow(vec![Method::Get, Method::Head, Method::Options])));
+}
+
+#[test]
+fn test_router_describe() {
+
+    let text = build_pipeline().describe().to_string();
+    assert!(text.starts_with("Router 5 routes\n    [GET /users]\n        Handle<{{closure}}>\n        (fallback) next\n"));
+}
@@ -0,0 +1,112 @@
+extern crate iron;
+extern crate iron_pipeline;
+extern crate iron_test;
+
+use iron::prelude::*;
+use iron::{ Headers, status };
+
+use iron_pipeline::prelude::*;
+use iron_pipeline::middleware::split::{ Sticky, SplitVariant };
+
+fn build_pipeline(sticky: Sticky, stable: u32, canary: u32) -> Pipeline {
+    let mut pipeline = Pipeline::new();
+    pipeline.add_named("canary", Fork::split(sticky)
+        .variant("stable", stable, |stable| {
+            stable.add(Handle(|_| Ok(Response::with((status::Ok, "stable")))));
+        })
+        .variant("canary", canary, |canary| {
+            canary.add(Handle(|req| {
+                assert_eq!(req.extensions.get::<SplitVariant>().unwrap(), "canary");
+                Ok(Response::with((status::Ok, "canary")))
+            }));
+        })
+        .response_header("X-Variant"));
+    pipeline
+}
+
+fn variant(headers: Headers, pipeline: &Pipeline) -> String {
+    let response = iron_test::request::get("http://localhost/", headers, pipeline).unwrap();
+    let header = String::from_utf8(response.headers.get_raw("X-Variant").unwrap()[0].clone()).unwrap();
+    let body = iron_test::response::extract_body_to_string(response);
+    assert_eq!(header, body);
+    body
+}
+
+fn user_headers(user: usize) -> Headers {
+    let mut headers = Headers::new();
+    headers.set_raw("X-User-Id", vec![format!("user-{}", user).into_bytes()]);
+    headers
+}
+
+#[test]
+fn test_split_sticky_header() {
+    let pipeline = build_pipeline(Sticky::Header("X-User-Id".to_string()), 80, 20);
+
+    let mut canaries = 0;
+    for user in 0..1000 {
+        let first = variant(user_headers(user), &pipeline);
+        // The same user is always assigned the same variant
+        assert_eq!(first, variant(user_headers(user), &pipeline));
+        if first == "canary" {
+            canaries += 1;
+        }
+    }
+    assert!(canaries > 150 && canaries < 250, "{} canaries", canaries);
+}
+
+#[test]
+fn test_split_weights() {
+    let pipeline = build_pipeline(Sticky::None, 100, 0);
+    for _ in 0..100 {
+        assert_eq!(variant(Headers::new(), &pipeline), "stable");
+    }
+
+    let pipeline = build_pipeline(Sticky::Attribute(Box::new(|req| req.url.query().map(|q| q.to_string()))), 0, 1);
+    assert_eq!(variant(Headers::new(), &pipeline), "canary");
+}
+
+#[test]
+fn test_split_sticky_cookie() {
+    let pipeline = build_pipeline(Sticky::Cookie("ab".to_string()), 50, 50);
+
+    // A cookie is assigned to new users
+    let response = iron_test::request::get("http://localhost/", Headers::new(), &pipeline).unwrap();
+    let cookie = String::from_utf8(response.headers.get_raw("Set-Cookie").unwrap()[0].clone()).unwrap();
+    assert!(cookie.starts_with("ab="));
+    assert!(cookie.ends_with("; Path=/; Max-Age=2592000"));
+    let chosen = iron_test::response::extract_body_to_string(response);
+
+    // ... and determines the variant of subsequent requests
+    let value = cookie.split(';').next().unwrap().to_string();
+    for _ in 0..10 {
+        let mut headers = Headers::new();
+        headers.set_raw("Cookie", vec![format!("other=1; {}", value).into_bytes()]);
+        let response = iron_test::request::get("http://localhost/", headers, &pipeline).unwrap();
+        assert!(response.headers.get_raw("Set-Cookie").is_none());
+        assert_eq!(iron_test::response::extract_body_to_string(response), chosen);
+    }
+}
+
+#[test]
+fn test_split_describe() {
+    let mut pipeline = build_pipeline(Sticky::Header("X-User-Id".to_string()), 90, 10);
+    assert!(pipeline.branch_mut("canary", Some("canary")).is_ok());
+
+    let description = pipeline.describe();
+    let split = &description.middlewares[0];
+    assert_eq!(split.kind, "Split");
+    assert_eq!(split.detail, Some("header(X-User-Id): stable=90, canary=10".to_string()));
+    assert_eq!(split.branches.len(), 2);
+}
+
+#[test]
+fn test_split_cookie_max_age() {
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Fork::split(Sticky::Cookie("ab".to_string()))
+        .variant("stable", 1, |stable| stable.add(Handle(|_| Ok(Response::with(status::Ok)))))
+        .cookie_max_age(3600));
+
+    let response = iron_test::request::get("http://localhost/", Headers::new(), &pipeline).unwrap();
+    let cookie = String::from_utf8(response.headers.get_raw("Set-Cookie").unwrap()[0].clone()).unwrap();
+    assert!(cookie.ends_with("; Path=/; Max-Age=3600"));
+}
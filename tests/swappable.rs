@@ -0,0 +1,59 @@
+extern crate iron;
+extern crate iron_pipeline;
+extern crate iron_test;
+
+use std::sync::{ Arc, Mutex };
+
+use iron::prelude::*;
+use iron::{ Headers, status };
+
+use iron_pipeline::prelude::*;
+
+fn build_pipeline(body: &'static str) -> Pipeline {
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Handle(move |_| {
+        Ok(Response::with((status::Ok, body)))
+    }));
+    pipeline
+}
+
+#[test]
+fn test_swap_pipeline() {
+
+    let swappable = SwappablePipeline::new(build_pipeline("first"));
+    let reloader = swappable.clone();
+
+    let response = iron_test::request::get("http://localhost/", Headers::new(), &swappable).unwrap();
+    assert_eq!(iron_test::response::extract_body_to_bytes(response), b"first");
+
+    reloader.swap(build_pipeline("second"));
+
+    let response = iron_test::request::get("http://localhost/", Headers::new(), &swappable).unwrap();
+    assert_eq!(iron_test::response::extract_body_to_bytes(response), b"second");
+}
+
+#[test]
+fn test_swap_pipeline_in_flight() {
+
+    // build a pipeline which swaps itself out while handling a request
+    let reloader: Arc<Mutex<Option<SwappablePipeline>>> = Arc::new(Mutex::new(None));
+    let mut pipeline = Pipeline::new();
+    {
+        let reloader = reloader.clone();
+        pipeline.add(Handle(move |_| {
+            let reloader = reloader.lock().unwrap().take().unwrap();
+            reloader.swap(build_pipeline("second"));
+            Ok(Response::with((status::Ok, "first")))
+        }));
+    }
+
+    let swappable = SwappablePipeline::new(pipeline);
+    *reloader.lock().unwrap() = Some(swappable.clone());
+
+    // the in-flight request completes on the original pipeline
+    let response = iron_test::request::get("http://localhost/", Headers::new(), &swappable).unwrap();
+    assert_eq!(iron_test::response::extract_body_to_bytes(response), b"first");
+
+    let response = iron_test::request::get("http://localhost/", Headers::new(), &swappable).unwrap();
+    assert_eq!(iron_test::response::extract_body_to_bytes(response), b"second");
+}
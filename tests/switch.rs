@@ -0,0 +1,112 @@
+extern crate iron;
+extern crate iron_pipeline;
+extern crate iron_test;
+
+use iron::prelude::*;
+use iron::{ Headers, status };
+
+use iron_pipeline::prelude::*;
+use iron_pipeline::middleware::fork::{ ForkTrail, MountPath };
+use iron_pipeline::middleware::switch::{ PathSegment, HeaderValue, HostName };
+
+fn get_body(path: &str, headers: Headers, pipeline: &Pipeline) -> Vec<u8> {
+    let response = iron_test::request::get(path, headers, pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Ok));
+    iron_test::response::extract_body_to_bytes(response)
+}
+
+#[test]
+fn test_switch_path_segment() {
+
+    let mut tenants = Switch::new(PathSegment);
+    for tenant in &["acme", "globex"] {
+        tenants.case(*tenant, |sub| {
+            sub.add(Handle(move |req| {
+                let mount = req.extensions.get::<MountPath>().unwrap().path_base();
+                let body = format!("{} {} /{}", tenant, mount, req.url.path().join("/"));
+                Ok(Response::with((status::Ok, body)))
+            }));
+        });
+    }
+
+    let mut pipeline = Pipeline::new();
+    pipeline.add(tenants);
+    pipeline.add(Handle(|_| Ok(Response::with(status::NotFound))));
+
+    assert_eq!(&get_body("http://localhost/acme/users/1", Headers::new(), &pipeline)[..], b"acme /acme /users/1");
+    assert_eq!(&get_body("http://localhost/globex", Headers::new(), &pipeline)[..], b"globex /globex /");
+
+    // Unknown keys fall through to the next middleware
+    let response = iron_test::request::get("http://localhost/initech/users", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NotFound));
+    let response = iron_test::request::get("http://localhost/", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NotFound));
+
+    // The key must be the first segment
+    let response = iron_test::request::get("http://localhost//acme/users", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NotFound));
+}
+
+#[test]
+fn test_switch_header_and_host() {
+
+    let mut versions = Switch::new(HeaderValue("X-ApiVersion"));
+    versions.case("1", |v1| { v1.add(Handle(|_| Ok(Response::with((status::Ok, "v1"))))); });
+    versions.case("2", |v2| { v2.add(Handle(|_| Ok(Response::with((status::Ok, "v2"))))); });
+
+    let mut hosts = Switch::new(HostName);
+    hosts.case("api.example.com", |api| { api.add(Handle(|_| Ok(Response::with((status::Ok, "api"))))); });
+
+    let mut pipeline = Pipeline::new();
+    pipeline.add(versions);
+    pipeline.add(hosts);
+    pipeline.add(Handle(|_| Ok(Response::with(status::NotFound))));
+
+    let mut headers = Headers::new();
+    headers.set_raw("X-ApiVersion", vec![b"2".to_vec()]);
+    assert_eq!(&get_body("http://localhost/", headers, &pipeline)[..], b"v2");
+    assert_eq!(&get_body("http://API.example.com/", Headers::new(), &pipeline)[..], b"api");
+
+    let response = iron_test::request::get("http://localhost/", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NotFound));
+}
+
+#[test]
+fn test_switch_closure_key() {
+
+    let mut tenants = Switch::new(|req: &Request| req.url.query().map(|q| q.to_string()));
+    tenants.case("tenant=acme", |acme| {
+        acme.add(Handle(|req| {
+            let trail = req.extensions.get::<ForkTrail>().unwrap().join(",");
+            Ok(Response::with((status::Ok, trail)))
+        }));
+    });
+
+    let mut pipeline = Pipeline::new();
+    pipeline.add(tenants);
+
+    assert_eq!(&get_body("http://localhost/?tenant=acme", Headers::new(), &pipeline)[..], b"switch(<fn>) = tenant=acme");
+}
+
+#[test]
+fn test_switch_describe_and_edit() {
+
+    let mut tenants = Switch::new(PathSegment);
+    tenants.case("globex", |_| {});
+    tenants.case("acme", |acme| { acme.add(Handle(|_| Ok(Response::with(status::Ok)))); });
+
+    let mut pipeline = Pipeline::new();
+    pipeline.add_named("tenants", tenants);
+
+    pipeline.branch_mut("tenants", Some("globex")).unwrap()
+        .add(Handle(|_| Ok(Response::with((status::Ok, "globex")))));
+    assert_eq!(&get_body("http://localhost/globex", Headers::new(), &pipeline)[..], b"globex");
+    assert!(pipeline.branch_mut("tenants", Some("initech")).is_err());
+
+    let description = pipeline.describe();
+    let switch = &description.middlewares[0];
+    assert_eq!(switch.kind, "Switch");
+    assert_eq!(switch.detail, Some("path_segment (2 cases)".to_string()));
+    let labels: Vec<_> = switch.branches.iter().map(|b| b.label.clone().unwrap()).collect();
+    assert_eq!(labels, vec!["acme", "globex"]);
+}
@@ -0,0 +1,121 @@
+extern crate iron;
+extern crate iron_pipeline;
+extern crate iron_test;
+
+use iron::prelude::*;
+use iron::{ Headers, status };
+
+use iron_pipeline::prelude::*;
+use iron_pipeline::middleware::version::{ VersionStrategy, VersionError, ApiVersion };
+
+fn echo_version(req: &mut Request) -> IronResult<Response> {
+    let version = req.extensions.get::<ApiVersion>().unwrap().clone();
+    Ok(Response::with((status::Ok, format!("{} /{}", version, req.url.path().join("/")))))
+}
+
+fn build_pipeline() -> Pipeline {
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Versioned::new()
+        .strategy(VersionStrategy::Header("X-ApiVersion".to_string()))
+        .strategy(VersionStrategy::Query("api-version".to_string()))
+        .strategy(VersionStrategy::PathSegment("v".to_string()))
+        .strategy(VersionStrategy::MediaType("version".to_string()))
+        .version("1", |v1| v1.add(Handle(echo_version)))
+        .version("2", |v2| v2.add(Handle(echo_version)))
+        .deprecated("1", Some("Sat, 01 Jan 2028 00:00:00 GMT")));
+    pipeline.add(Handle(|_| Ok(Response::with(status::NotFound))));
+    pipeline
+}
+
+fn get_body(url: &str, headers: Headers, pipeline: &Pipeline) -> String {
+    let response = iron_test::request::get(url, headers, pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Ok));
+    iron_test::response::extract_body_to_string(response)
+}
+
+fn header(name: &'static str, value: &str) -> Headers {
+    let mut headers = Headers::new();
+    headers.set_raw(name, vec![value.as_bytes().to_vec()]);
+    headers
+}
+
+#[test]
+fn test_version_strategies() {
+    let pipeline = build_pipeline();
+
+    assert_eq!(get_body("http://localhost/users", header("X-ApiVersion", "2"), &pipeline), "2 /users");
+    assert_eq!(get_body("http://localhost/users?api-version=2", Headers::new(), &pipeline), "2 /users");
+    assert_eq!(get_body("http://localhost/v2/users", Headers::new(), &pipeline), "2 /users");
+    assert_eq!(get_body("http://localhost/users", header("Accept", "text/html, application/json; version=\"2\""), &pipeline), "2 /users");
+
+    // Strategies are tried in order
+    assert_eq!(get_body("http://localhost/users?api-version=1", header("X-ApiVersion", "2"), &pipeline), "2 /users");
+
+    // Requests without a version are passed on
+    let response = iron_test::request::get("http://localhost/users", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NotFound));
+}
+
+#[test]
+fn test_version_deprecated() {
+    let pipeline = build_pipeline();
+
+    let response = iron_test::request::get("http://localhost/v1/users", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.headers.get_raw("Deprecation").unwrap()[0], b"true".to_vec());
+    assert_eq!(response.headers.get_raw("Sunset").unwrap()[0], b"Sat, 01 Jan 2028 00:00:00 GMT".to_vec());
+
+    let response = iron_test::request::get("http://localhost/v2/users", Headers::new(), &pipeline).unwrap();
+    assert!(response.headers.get_raw("Deprecation").is_none());
+}
+
+#[test]
+fn test_version_path_segment_requires_version() {
+    let mut pipeline = Pipeline::new();
+    pipeline.add(Versioned::new()
+        .strategy(VersionStrategy::PathSegment("v".to_string()))
+        .version("1", |v1| v1.add(Handle(echo_version)))
+        .version("beta", |beta| beta.add(Handle(echo_version))));
+    pipeline.add(Handle(|req| Ok(Response::with((status::NotFound, format!("/{}", req.url.path().join("/")))))));
+
+    // Paths which merely start with the prefix are passed on, unmodified
+    let response = iron_test::request::get("http://localhost/videos/1", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::NotFound));
+    assert_eq!(iron_test::response::extract_body_to_string(response), "/videos/1");
+
+    // Registered versions need not look like versions
+    assert_eq!(get_body("http://localhost/vbeta/users", Headers::new(), &pipeline), "beta /users");
+
+    // Unregistered versions are still rejected
+    let err = iron_test::request::get("http://localhost/v2.1/users", Headers::new(), &pipeline).unwrap_err();
+    assert_eq!(err.response.status, Some(status::BadRequest));
+}
+
+#[test]
+fn test_version_unknown() {
+    let pipeline = build_pipeline();
+
+    let err = iron_test::request::get("http://localhost/v3/users", Headers::new(), &pipeline).unwrap_err();
+    assert_eq!(err.response.status, Some(status::BadRequest));
+    let version_err = err.error.downcast_ref::<VersionError>().unwrap();
+    assert_eq!(version_err.requested, "3");
+    assert_eq!(version_err.supported, vec!["1", "2"]);
+    assert_eq!(version_err.to_string(), "Unsupported API version \"3\" (supported versions: 1, 2)");
+}
+
+#[test]
+fn test_version_default_and_describe() {
+    let mut pipeline = Pipeline::new();
+    pipeline.add_named("api", Versioned::new()
+        .strategy(VersionStrategy::Header("X-ApiVersion".to_string()))
+        .version("1", |_| {})
+        .version("2", |_| {})
+        .deprecated("1", None)
+        .default_version("2"));
+
+    pipeline.branch_mut("api", Some("2")).unwrap().add(Handle(echo_version));
+    assert_eq!(get_body("http://localhost/users", Headers::new(), &pipeline), "2 /users");
+
+    let description = pipeline.describe();
+    assert_eq!(description.middlewares[0].detail, Some("header(X-ApiVersion) default=2 deprecated=1".to_string()));
+    assert_eq!(description.middlewares[0].branches.len(), 2);
+}
@@ -0,0 +1,92 @@
+extern crate iron;
+extern crate iron_pipeline;
+extern crate iron_test;
+
+use iron::prelude::*;
+use iron::{ Headers, status };
+use iron::method::Post;
+
+use iron_pipeline::prelude::*;
+use iron_pipeline::predicate::{ Predicate, Method, Header };
+
+fn build_pipeline() -> Pipeline {
+    let mut pipeline = Pipeline::new();
+
+    // Require a token for requests to "/admin"
+    pipeline.add_when(|req: &Request| req.url.path()[0] == "admin", HandleNext(|req, next| {
+        match req.headers.get_raw("X-Token") {
+            Some(_) => next.process(req),
+            None    => Ok(Response::with(status::Unauthorized))
+        }
+    }));
+
+    // Tag POST requests, then rejoin the pipeline
+    pipeline.add(When(Method(Post), HandleNext(|req, next| {
+        let mut response = next.process(req)?;
+        response.headers.set_raw("X-Posted", vec![b"1".to_vec()]);
+        Ok(response)
+    })));
+
+    pipeline.add(Handle(|req| Ok(Response::with((status::Ok, req.url.path().join("/"))))));
+    pipeline
+}
+
+#[test]
+fn test_when_rejoins_pipeline() {
+    let pipeline = build_pipeline();
+
+    let response = iron_test::request::get("http://localhost/admin/users", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Unauthorized));
+
+    let mut headers = Headers::new();
+    headers.set_raw("X-Token", vec![b"secret".to_vec()]);
+    let response = iron_test::request::get("http://localhost/admin/users", headers, &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Ok));
+    let result_body = iron_test::response::extract_body_to_bytes(response);
+    assert_eq!(&result_body[..], b"admin/users");
+
+    let response = iron_test::request::get("http://localhost/users", Headers::new(), &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Ok));
+    assert!(response.headers.get_raw("X-Posted").is_none());
+
+    let response = iron_test::request::post("http://localhost/users", Headers::new(), "", &pipeline).unwrap();
+    assert_eq!(response.status, Some(status::Ok));
+    assert!(response.headers.get_raw("X-Posted").is_some());
+}
+
+#[test]
+fn test_when_describe() {
+    let mut pipeline = Pipeline::new();
+    pipeline.add(When(Method(Post).and(Header("X-Token", "1").not()), Handle(|_| Ok(Response::with(status::Unauthorized)))));
+    pipeline.add(Handle(|_| Ok(Response::with(status::Ok))));
+
+    let description = pipeline.describe();
+    let when = &description.middlewares[0];
+    assert_eq!(when.kind, "When");
+    assert_eq!(when.detail, Some("(method(POST) and not(header(X-Token = 1))) => Handle<{{closure}}>".to_string()));
+    assert!(!when.terminal);
+    assert!(when.branches.is_empty());
+
+    // The wrapped handler does not make the rest of the pipeline unreachable
+    assert!(pipeline.build().is_ok());
+}
+
+#[test]
+fn test_when_describe_matches_branch_mut() {
+    let mut pipeline = Pipeline::new();
+    pipeline.add(When(Method(Post), Fork::when_path("/api", |api| {
+        api.add(Handle(|_| Ok(Response::with(status::Ok))));
+    })));
+
+    let description = pipeline.describe();
+    let when = &description.middlewares[0];
+    assert_eq!(when.detail, Some("method(POST) => Fork when_path(/api)".to_string()));
+    assert_eq!(when.path, Some("/api".to_string()));
+    assert_eq!(when.branches.len(), 1);
+    assert_eq!(when.branches[0].label, None);
+
+    // The described branch is the branch which may be edited
+    pipeline.branch_mut(0, None).unwrap().insert_before(0, Handle(|_| Ok(Response::with(status::Accepted)))).unwrap();
+    let description = pipeline.describe();
+    assert_eq!(description.middlewares[0].branches[0].pipeline.middlewares.len(), 2);
+}